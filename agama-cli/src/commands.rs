@@ -1,5 +1,7 @@
 use crate::config::ConfigCommands;
 use crate::profile::ProfileCommands;
+use crate::storage::StorageCommands;
+use agama_lib::cmdline::CMDLINE_PATH;
 use clap::Subcommand;
 
 #[derive(Subcommand, Debug)]
@@ -16,8 +18,27 @@ pub enum Commands {
     /// Start probing
     Probe,
     // Start Installation
-    Install,
+    Install {
+        /// Install even if existing data would be deleted (e.g., by the "delete" space policy)
+        #[arg(long)]
+        allow_destructive: bool,
+    },
     /// Autoinstallation profile handling
     #[command(subcommand)]
     Profile(ProfileCommands),
+    /// Storage devices information
+    #[command(subcommand)]
+    Storage(StorageCommands),
+    /// Run the autoinstallation as requested in the kernel command line
+    ///
+    /// It loads the profile given in agama.auto=<url>, applies the agama.<key>=<value>
+    /// overrides and, if agama.install=1 is given, starts the installation.
+    Auto {
+        /// File to read the kernel command line from
+        #[arg(long, default_value = CMDLINE_PATH)]
+        cmdline: String,
+        /// Install even if existing data would be deleted
+        #[arg(long)]
+        allow_destructive: bool,
+    },
 }
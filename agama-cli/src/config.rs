@@ -1,98 +1,713 @@
 use crate::error::CliError;
 use crate::printers::{print, Format};
-use clap::Subcommand;
-use convert_case::{Case, Casing};
 use agama_lib::connection;
+use agama_lib::diff::{diff, masked, ChangeKind, SettingChange, MASKED_SECRET};
 use agama_lib::install_settings::{InstallSettings, Scope};
-use agama_lib::settings::{SettingObject, SettingValue, Settings};
+use agama_lib::layers::{
+    defined_values, env_settings, LayeredSettings, Origin, Origins, ORIGINS_PATH,
+};
+use agama_lib::profile::{export_profile, read_profile, SecretsPolicy};
+use agama_lib::settings::{
+    read_values, ArgValue, ConversionError, MergeStrategy, SettingArg, SettingDescription,
+    SettingObject, SettingOperator, Settings, SettingsError, ValueSource,
+};
 use agama_lib::Store as SettingsStore;
-use std::str::FromStr;
-use std::{collections::HashMap, error::Error, io};
+use clap::Subcommand;
+use console::Term;
+use std::fs::File;
+use std::io::Write;
+use std::{collections::HashMap, env, error::Error, io, path::Path};
 
 #[derive(Subcommand, Debug)]
 pub enum ConfigCommands {
     /// Add an element to a collection
-    Add { key: String, values: Vec<String> },
+    Add {
+        /// Collection (e.g., storage.devices)
+        key: String,
+        /// Values of the element (e.g., name=/dev/sda)
+        values: Vec<SettingArg>,
+    },
+    /// Remove the elements of a collection which match the given values
+    Remove {
+        /// Collection (e.g., storage.devices)
+        key: String,
+        /// key-value pairs (e.g., name=/dev/sda)
+        values: Vec<SettingArg>,
+    },
     /// Set one or many installation settings
+    ///
+    /// Besides key=value, it supports key:=<json> (e.g., software.patterns:='["base"]'), which
+    /// can replace a whole collection, and key+=item to add an item to a list or a collection.
+    /// A value like @path is read from a file and @- from the standard input. Secrets (e.g.,
+    /// root.password) can be read from a prompt (@prompt), a file (@file:path) or an
+    /// environment variable (@env:VAR) so they do not show up in the process list.
     Set {
-        /// key-value pairs (e.g., user.name="Jane Doe")
-        values: Vec<String>,
+        /// Settings to change (e.g., user.fullName="Jane Doe")
+        values: Vec<SettingArg>,
+    },
+    /// Unset one or many installation settings
+    ///
+    /// Unsetting the user name (user.userName) removes the first user. The settings which always
+    /// need a value (the product, the language, the keyboard and the iSCSI initiator) cannot be
+    /// unset.
+    Unset {
+        /// Settings to unset (e.g., root.password)
+        keys: Vec<String>,
+    },
+    /// Resets the settings of the given scopes
+    ///
+    /// The selected product and the language are kept.
+    Reset {
+        /// Scopes to reset (e.g., users,storage)
+        #[arg(long, value_delimiter = ',', required = true)]
+        scope: Vec<Scope>,
     },
     /// Shows the value of one or many configuration settings
-    Show,
-    /// Loads the configuration from a JSON file
-    Load { path: String },
+    ///
+    /// The secrets (e.g., user.password) are masked.
+    Show {
+        /// Shows where each value comes from (installer, profile, cmdline or env)
+        #[arg(long)]
+        origin: bool,
+        /// Prints the secrets in clear text
+        #[arg(long)]
+        show_secrets: bool,
+    },
+    /// Describes the given setting or, if none is given, all the known settings
+    Describe { key: Option<String> },
+    /// Prints the value of the given settings, one per line
+    ///
+    /// Strings are printed as they are and other values as JSON. Use --format to get the values
+    /// as a JSON or YAML object. The secrets (e.g., user.password) are masked.
+    Get {
+        /// Settings to print (e.g., software.product user.userName)
+        keys: Vec<String>,
+        /// Prints the secrets in clear text
+        #[arg(long)]
+        show_secrets: bool,
+    },
+    /// Loads the configuration from a JSON or YAML file
+    Load {
+        path: String,
+        /// How to merge the collections (replace, append or merge-by-key); each collection
+        /// uses its own default if not given
+        #[arg(long)]
+        strategy: Option<MergeStrategy>,
+    },
+    /// Exports the configuration as a profile which can be loaded later
+    Export {
+        /// Scopes to export (e.g., users,software); all of them by default
+        #[arg(long, value_delimiter = ',')]
+        scope: Vec<Scope>,
+        /// File to write the profile to; the standard output by default
+        #[arg(long, short)]
+        output: Option<String>,
+        /// What to do with the secrets (keep, omit or placeholder, which asks for them when
+        /// loading the profile)
+        #[arg(long, default_value = "placeholder")]
+        secrets: SecretsPolicy,
+    },
+    /// Shows the changes that loading a profile would introduce in the current configuration
+    ///
+    /// If a second profile is given, it compares both profiles instead.
+    Diff {
+        /// Profile to compare (JSON or YAML)
+        path: String,
+        /// Profile to compare against the first one
+        other: Option<String>,
+        /// Exit with 1 if there are changes and 0 otherwise
+        #[arg(long)]
+        exit_code: bool,
+    },
 }
 
-pub enum ConfigAction {
-    Add(String, HashMap<String, String>),
-    Set(HashMap<String, String>),
-    Show,
-    Load(String),
+impl ConfigCommands {
+    /// Whether the subcommand needs the Agama D-Bus service
+    pub fn needs_service(&self) -> bool {
+        !matches!(
+            self,
+            Self::Diff { other: Some(_), .. } | Self::Describe { .. }
+        )
+    }
 }
 
-pub async fn run(subcommand: ConfigCommands, format: Format) -> Result<(), Box<dyn Error>> {
-    let store = SettingsStore::new(connection().await?).await?;
+pub enum ConfigAction {
+    Add(String, Vec<SettingArg>),
+    Remove(String, Vec<SettingArg>),
+    Set(Vec<SettingArg>),
+    Unset(Vec<String>),
+    Reset(Vec<Scope>),
+    Show(bool, bool),
+    Get(Vec<String>, bool),
+    Describe(Option<String>),
+    Load(String, Option<MergeStrategy>),
+    Export(Vec<Scope>, Option<String>, SecretsPolicy),
+    Diff(String, Option<String>, bool),
+}
 
+pub async fn run(subcommand: ConfigCommands, format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    // only the actions which need the service connect to it (see ConfigCommands::needs_service)
+    let store = || async { SettingsStore::new(connection().await?).await };
     match parse_config_command(subcommand) {
-        ConfigAction::Set(changes) => {
-            let scopes = changes
-                .keys()
-                .filter_map(|k| key_to_scope(k).ok())
-                .collect();
+        ConfigAction::Set(args) => {
+            let store = store().await?;
+            let settings = args
+                .iter()
+                .map(|arg| find_setting(&arg.key, None))
+                .collect::<Result<Vec<_>, CliError>>()?;
+            for (arg, setting) in args.iter().zip(&settings) {
+                if arg.source == ValueSource::Prompt && !setting.secret {
+                    return Err(CliError::NotASecret(setting.key.clone()).into());
+                }
+            }
+            let values = read_values(&args, prompt_secret)?;
+            let scopes = settings.iter().filter_map(|s| s.scope).collect();
             let mut model = store.load(Some(scopes)).await?;
-            for (key, value) in changes {
-                model.set(&key.to_case(Case::Snake), SettingValue(value))?;
+            // null values are not written when storing the model, so unset them explicitly
+            let mut unset = vec![];
+            for ((arg, setting), value) in args.iter().zip(&settings).zip(values) {
+                if value == ArgValue::Json(serde_json::Value::Null) {
+                    unset.push(setting.attr.clone());
+                }
+                set_value(&mut model, setting, arg.operator, value)?;
             }
-            store.store(&model).await
+            store.unset(&unset).await?;
+            store.store(&model).await?;
+            forget_origins(&settings);
+            Ok(())
         }
-        ConfigAction::Show => {
-            let model = store.load(None).await?;
-            print(model, io::stdout(), format)?;
+        ConfigAction::Unset(keys) => {
+            let store = store().await?;
+            let settings = keys
+                .iter()
+                .map(|k| find_setting(k, None))
+                .collect::<Result<Vec<_>, CliError>>()?;
+            let attrs: Vec<_> = settings.iter().map(|s| s.attr.clone()).collect();
+            store.unset(&attrs).await?;
+            forget_origins(&settings);
             Ok(())
         }
-        ConfigAction::Add(key, values) => {
-            let scope = key_to_scope(&key).unwrap();
-            let mut model = store.load(Some(vec![scope])).await?;
-            model.add(&key.to_case(Case::Snake), SettingObject::from(values))?;
-            store.store(&model).await
+        ConfigAction::Reset(scopes) => {
+            let store = store().await?;
+            store.reset(&scopes).await?;
+            let settings: Vec<_> = InstallSettings::settings()
+                .into_iter()
+                .filter(|s| s.scope.is_some_and(|scope| scopes.contains(&scope)))
+                .collect();
+            forget_origins(&settings);
+            Ok(())
         }
-        ConfigAction::Load(path) => {
-            let contents = std::fs::read_to_string(path)?;
-            let result: InstallSettings = serde_json::from_str(&contents)?;
-            let scopes = result.defined_scopes();
-            let mut model = store.load(Some(scopes)).await?;
-            model.merge(&result);
-            store.store(&model).await
+        ConfigAction::Describe(key) => describe(key.as_deref(), format),
+        ConfigAction::Show(origin, show_secrets) => {
+            let store = store().await?;
+            let mut model = store.load(None).await?;
+            if !show_secrets {
+                model = masked(&model);
+            }
+            if origin {
+                let origins = Origins::read(Path::new(ORIGINS_PATH))?;
+                return show_origins(&model, &origins, format);
+            }
+            print(model, io::stdout(), format.unwrap_or(Format::Json))?;
+            Ok(())
+        }
+        ConfigAction::Get(keys, show_secrets) => {
+            let store = store().await?;
+            let settings = keys
+                .iter()
+                .map(|k| find_setting(k, None))
+                .collect::<Result<Vec<_>, _>>()?;
+            let scopes = settings.iter().filter_map(|s| s.scope).collect();
+            let model = store.load(Some(scopes)).await?;
+            let values = keys
+                .into_iter()
+                .zip(settings)
+                .map(|(key, setting)| {
+                    let mut value = model.get(&setting.attr)?;
+                    if !show_secrets {
+                        mask_secrets(&setting, &mut value);
+                    }
+                    Ok((key, value))
+                })
+                .collect::<Result<Vec<_>, SettingsError>>()?;
+            match format {
+                Some(format @ (Format::Json | Format::Yaml)) => {
+                    let values: serde_json::Map<_, _> = values.into_iter().collect();
+                    print(values, io::stdout(), format)?
+                }
+                // keep the order of the keys, so the values can be easily read in a script
+                _ => {
+                    for (_, value) in values {
+                        match value {
+                            serde_json::Value::String(value) => println!("{}", value),
+                            serde_json::Value::Null => println!(),
+                            value => println!("{}", value),
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        ConfigAction::Add(key, args) => {
+            let store = store().await?;
+            let setting = find_setting(&key, Some(true))?;
+            let element = setting_object(&setting, &args, prompt_secret)?;
+            let mut model = store
+                .load(Some(setting.scope.into_iter().collect()))
+                .await?;
+            model.add(&setting.attr, element)?;
+            store.store(&model).await?;
+            forget_origins(&[setting]);
+            Ok(())
+        }
+        ConfigAction::Remove(key, args) => {
+            let store = store().await?;
+            let setting = find_setting(&key, Some(true))?;
+            let element = setting_object(&setting, &args, prompt_secret)?;
+            let mut model = store
+                .load(Some(setting.scope.into_iter().collect()))
+                .await?;
+            model.remove(&setting.attr, element)?;
+            store.store(&model).await?;
+            forget_origins(&[setting]);
+            Ok(())
+        }
+        ConfigAction::Load(path, strategy) => {
+            let store = store().await?;
+            let settings: InstallSettings = read_profile(Path::new(&path))?;
+            let mut layers = LayeredSettings::default();
+            layers.add_layer(&settings, Origin::Profile, None);
+            load_settings(&store, layers, strategy).await
+        }
+        ConfigAction::Export(scopes, output, secrets) => {
+            let store = store().await?;
+            let scopes = if scopes.is_empty() {
+                None
+            } else {
+                Some(scopes)
+            };
+            let model = store.load(scopes).await?;
+            let profile = export_profile(model, secrets)?;
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            print(profile, writer, format.unwrap_or(Format::Json))
+        }
+        ConfigAction::Diff(path, Some(other), exit_code) => {
+            let from: InstallSettings = read_profile(Path::new(&path))?;
+            let to: InstallSettings = read_profile(Path::new(&other))?;
+            show_diff(diff(&from, &to), format, exit_code)
+        }
+        ConfigAction::Diff(path, None, exit_code) => {
+            let store = store().await?;
+            let settings: InstallSettings = read_profile(Path::new(&path))?;
+            let current = store.load(Some(settings.defined_scopes())).await?;
+            let mut updated = current.clone();
+            updated.merge(&settings);
+            show_diff(diff(&current, &updated), format, exit_code)
         }
     }
 }
 
+/// Prints the changes, exiting with 1 if `exit_code` is set and there are changes
+fn show_diff(
+    changes: Vec<SettingChange>,
+    format: Option<Format>,
+    exit_code: bool,
+) -> Result<(), Box<dyn Error>> {
+    let has_changes = !changes.is_empty();
+    match format {
+        Some(format @ (Format::Json | Format::Yaml)) => print(changes, io::stdout(), format)?,
+        _ => {
+            for change in changes {
+                let (old, new) = (
+                    change.old.unwrap_or_default(),
+                    change.new.unwrap_or_default(),
+                );
+                match change.kind {
+                    ChangeKind::Added => println!("+ {}: {}", change.key, new),
+                    ChangeKind::Changed => println!("~ {}: {} -> {}", change.key, old, new),
+                    ChangeKind::Removed => println!("- {}: {}", change.key, old),
+                }
+            }
+        }
+    }
+
+    if exit_code && has_changes {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Merges the given layers into the current settings and stores the result
+///
+/// The environment variables (e.g., `AGAMA_SOFTWARE_PRODUCT`) are added as the topmost layer
+/// and the origin of each value is recorded in [ORIGINS_PATH]. Only the scopes defined in the
+/// resulting settings are read and written. The collections are merged according to `strategy`
+/// (see [Settings::merge_with]). The references in the secrets (e.g.,
+/// `"@env:ROOT_PASSWORD"`) are resolved before merging.
+pub async fn load_settings(
+    store: &SettingsStore<'_>,
+    mut layers: LayeredSettings,
+    strategy: Option<MergeStrategy>,
+) -> Result<(), Box<dyn Error>> {
+    layers.add_layer(&env_settings(env::vars())?, Origin::Env, None);
+    let LayeredSettings {
+        mut settings,
+        origins,
+    } = layers;
+
+    settings.resolve_secrets(prompt_secret)?;
+    let scopes = settings.defined_scopes();
+    let mut model = store.load(Some(scopes)).await?;
+    model.merge_with(&settings, strategy);
+    store.store(&model).await?;
+
+    update_origins(|all_origins| all_origins.extend(origins));
+    Ok(())
+}
+
+/// Records that the given settings were changed in the installer, forgetting their origin
+fn forget_origins(settings: &[SettingDescription]) {
+    update_origins(|origins| {
+        origins.set(settings.iter().map(|s| s.key.as_str()), Origin::Installer)
+    });
+}
+
+/// Updates the origins in [ORIGINS_PATH]
+///
+/// The settings are already stored at this point, so a failure is reported as a warning instead
+/// of making the whole command fail.
+fn update_origins(f: impl FnOnce(&mut Origins)) {
+    let path = Path::new(ORIGINS_PATH);
+    let result = Origins::read(path).and_then(|origins| {
+        let mut updated = origins.clone();
+        f(&mut updated);
+        if updated != origins {
+            updated.write(path)?;
+        }
+        Ok(())
+    });
+    if let Err(error) = result {
+        eprintln!("Warning: could not record the origin of the settings: {error}");
+    }
+}
+
+/// Prints the defined settings and where their values come from
+fn show_origins(
+    model: &InstallSettings,
+    origins: &Origins,
+    format: Option<Format>,
+) -> Result<(), Box<dyn Error>> {
+    let values: Vec<_> = defined_values(model)
+        .into_iter()
+        .map(|(key, value)| {
+            let origin = origins.get(&key);
+            (key, value, origin)
+        })
+        .collect();
+
+    match format {
+        Some(format @ (Format::Json | Format::Yaml)) => {
+            let values: serde_json::Map<_, _> = values
+                .into_iter()
+                .map(|(key, value, origin)| {
+                    (key, serde_json::json!({ "value": value, "origin": origin }))
+                })
+                .collect();
+            print(values, io::stdout(), format)?
+        }
+        _ => {
+            for (key, value, origin) in values {
+                println!("{}: {} ({})", key, value, origin);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn parse_config_command(subcommand: ConfigCommands) -> ConfigAction {
     match subcommand {
-        ConfigCommands::Add { key, values } => ConfigAction::Add(key, parse_keys_values(values)),
-        ConfigCommands::Show => ConfigAction::Show,
-        ConfigCommands::Set { values } => ConfigAction::Set(parse_keys_values(values)),
-        ConfigCommands::Load { path } => ConfigAction::Load(path),
+        ConfigCommands::Add { key, values } => ConfigAction::Add(key, values),
+        ConfigCommands::Show {
+            origin,
+            show_secrets,
+        } => ConfigAction::Show(origin, show_secrets),
+        ConfigCommands::Get { keys, show_secrets } => ConfigAction::Get(keys, show_secrets),
+        ConfigCommands::Describe { key } => ConfigAction::Describe(key),
+        ConfigCommands::Remove { key, values } => ConfigAction::Remove(key, values),
+        ConfigCommands::Set { values } => ConfigAction::Set(values),
+        ConfigCommands::Unset { keys } => ConfigAction::Unset(keys),
+        ConfigCommands::Reset { scope } => ConfigAction::Reset(scope),
+        ConfigCommands::Load { path, strategy } => ConfigAction::Load(path, strategy),
+        ConfigCommands::Export {
+            scope,
+            output,
+            secrets,
+        } => ConfigAction::Export(scope, output, secrets),
+        ConfigCommands::Diff {
+            path,
+            other,
+            exit_code,
+        } => ConfigAction::Diff(path, other, exit_code),
     }
 }
 
-fn parse_keys_values(keys_values: Vec<String>) -> HashMap<String, String> {
-    keys_values
-        .iter()
-        .filter_map(|s| {
-            if let Some((key, value)) = s.split_once('=') {
-                Some((key.to_string(), value.to_string()))
-            } else {
-                None
+/// Applies the value of a `config set` argument to the given setting
+fn set_value(
+    model: &mut InstallSettings,
+    setting: &SettingDescription,
+    operator: SettingOperator,
+    value: ArgValue,
+) -> Result<(), Box<dyn Error>> {
+    let attr = &setting.attr;
+    let wrong_value = |e: ConversionError| SettingsError::from(e).in_section(&setting.key);
+    if value == ArgValue::Json(serde_json::Value::Null) {
+        return Ok(model.unset(attr)?);
+    }
+
+    match (operator, setting.collection) {
+        (SettingOperator::Set, true) => {
+            return Err(CliError::CollectionKey(setting.key.clone()).into())
+        }
+        (SettingOperator::Set | SettingOperator::SetJson, false) => {
+            model.set(attr, value.to_setting_value().map_err(wrong_value)?)?
+        }
+        (SettingOperator::SetJson, true) => {
+            let elements = value.to_setting_objects().map_err(wrong_value)?;
+            model.unset(attr)?;
+            for element in elements {
+                model.add(attr, element)?;
             }
-        })
-        .collect()
+        }
+        (SettingOperator::Append, true) => {
+            model.add(attr, value.to_setting_object().map_err(wrong_value)?)?
+        }
+        (SettingOperator::Append, false) if setting.value_type == "list" => {
+            let item = value.to_setting_value().map_err(wrong_value)?;
+            append_item(model, &setting.key, item.0)?
+        }
+        (SettingOperator::Append, false) => {
+            return Err(CliError::NotAList(setting.key.clone()).into())
+        }
+    }
+    Ok(())
+}
+
+/// Appends an item to a list setting (e.g., `software.patterns`)
+///
+/// The list is extended in the profile form of the settings, so the items are not split on
+/// commas as when setting the whole list from a [agama_lib::settings::SettingValue].
+fn append_item(model: &mut InstallSettings, key: &str, item: String) -> Result<(), Box<dyn Error>> {
+    let mut profile = model.to_profile();
+    let mut node = &mut profile;
+    for part in key.split('.') {
+        let Some(section) = node.as_object_mut() else {
+            return Err(CliError::NotAList(key.to_string()).into());
+        };
+        node = section
+            .entry(part)
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
+    }
+    match node {
+        serde_json::Value::Array(items) => items.push(item.into()),
+        // an empty section was inserted for the missing list
+        serde_json::Value::Object(fields) if fields.is_empty() => *node = vec![item].into(),
+        _ => return Err(CliError::NotAList(key.to_string()).into()),
+    }
+    *model = serde_json::from_value(profile)?;
+    Ok(())
+}
+
+/// Builds an element of a collection from `field=value` arguments
+///
+/// The `prompt` function is called for the secret fields whose value is `@prompt`.
+fn setting_object(
+    setting: &SettingDescription,
+    args: &[SettingArg],
+    mut prompt: impl FnMut(&str) -> io::Result<String>,
+) -> Result<SettingObject, Box<dyn Error>> {
+    for arg in args {
+        let key = format!("{}.{}", setting.key, arg.key);
+        if arg.operator == SettingOperator::Append {
+            return Err(CliError::NotAList(key).into());
+        }
+        if arg.source == ValueSource::Prompt && !setting.secret_fields.contains(&arg.key) {
+            return Err(CliError::NotASecret(key).into());
+        }
+    }
+
+    let values = read_values(args, |field| prompt(&format!("{}.{}", setting.key, field)))?;
+    let mut object = HashMap::new();
+    for (arg, value) in args.iter().zip(values) {
+        let key = format!("{}.{}", setting.key, arg.key);
+        let value = value
+            .to_setting_value()
+            .map_err(|e| SettingsError::from(e).in_section(&key))?;
+        object.insert(arg.key.clone(), value);
+    }
+    Ok(SettingObject(object))
+}
+
+/// Asks the user for the value of a secret, without echoing it
+///
+/// The value must be typed twice to avoid typos.
+pub(crate) fn prompt_secret(key: &str) -> io::Result<String> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(io::Error::other("not a terminal"));
+    }
+    term.write_str(&format!("{key}: "))?;
+    let value = term.read_secure_line()?;
+    term.write_str(&format!("Confirm {key}: "))?;
+    if term.read_secure_line()? != value {
+        return Err(io::Error::other("the values do not match"));
+    }
+    Ok(value)
+}
+
+/// Finds a setting in the registry, suggesting a similar key if it does not exist
+///
+/// * `key`: setting key (e.g., "user.userName").
+/// * `collection`: whether the setting must be a collection or not (`None` means any).
+fn find_setting(key: &str, collection: Option<bool>) -> Result<SettingDescription, CliError> {
+    let Some(setting) = InstallSettings::find_setting(key) else {
+        return Err(match InstallSettings::suggest_setting(key) {
+            Some(suggestion) => CliError::UnknownKey(key.to_string(), suggestion),
+            None => CliError::InvalidKeyName(key.to_string()),
+        });
+    };
+
+    match collection {
+        Some(true) if !setting.collection => Err(CliError::NotACollection(setting.key)),
+        Some(false) if setting.collection => Err(CliError::CollectionKey(setting.key)),
+        _ => Ok(setting),
+    }
 }
 
-fn key_to_scope(key: &str) -> Result<Scope, Box<dyn Error>> {
-    if let Some((name, _)) = key.split_once('.') {
-        return Ok(Scope::from_str(name)?);
+/// Describes the given setting or all the known settings
+fn describe(key: Option<&str>, format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let settings = match key {
+        Some(key) => vec![find_setting(key, None)?],
+        None => InstallSettings::settings(),
+    };
+    match format {
+        Some(format @ (Format::Json | Format::Yaml)) => print(settings, io::stdout(), format)?,
+        _ => settings.iter().for_each(print_description),
+    }
+    Ok(())
+}
+
+/// Replaces the secrets in the value of a setting with [MASKED_SECRET]
+///
+/// It handles secret settings and the secret fields of the elements of a collection.
+fn mask_secrets(setting: &SettingDescription, value: &mut serde_json::Value) {
+    let mask = |value: &mut serde_json::Value| {
+        if value.as_str().is_some_and(|v| !v.is_empty()) {
+            *value = MASKED_SECRET.into();
+        }
+    };
+    if setting.secret {
+        mask(value);
+    }
+    if let serde_json::Value::Array(elements) = value {
+        for element in elements {
+            for field in &setting.secret_fields {
+                if let Some(value) = element.get_mut(field) {
+                    mask(value);
+                }
+            }
+        }
+    }
+}
+
+/// Prints the description of a setting
+fn print_description(setting: &SettingDescription) {
+    let kind = if setting.collection && !setting.secret_fields.is_empty() {
+        format!(
+            "collection, secret fields: {}",
+            setting.secret_fields.join(", ")
+        )
+    } else if setting.collection {
+        "collection".to_string()
+    } else if setting.secret {
+        format!("{}, secret", setting.value_type)
+    } else {
+        setting.value_type.clone()
+    };
+    println!("{} ({})", setting.key, kind);
+    println!("  {}", setting.description);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<SettingArg> {
+        args.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_setting_object_secrets() {
+        let setting = find_setting("iscsi.targets", Some(true)).unwrap();
+        assert_eq!(setting.secret_fields, ["password", "reversePassword"]);
+
+        let mut prompted = vec![];
+        let object = setting_object(
+            &setting,
+            &args(&["name=iqn.2023-01.com.example:storage", "password=@prompt"]),
+            |key| {
+                prompted.push(key.to_string());
+                Ok("s3cr3t".to_string())
+            },
+        )
+        .unwrap();
+        assert_eq!(object.get("password").unwrap().0, "s3cr3t");
+        assert_eq!(prompted, ["iscsi.targets.password"]);
+
+        let Err(error) = setting_object(&setting, &args(&["address=@prompt"]), |_| {
+            Ok("192.168.1.10".to_string())
+        }) else {
+            panic!("the address is not a secret");
+        };
+        assert_eq!(
+            error.to_string(),
+            CliError::NotASecret("iscsi.targets.address".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_append_item() {
+        let mut model = InstallSettings::default();
+        let setting = find_setting("software.patterns", None).unwrap();
+        for item in ["base", "a,b", "gnome"] {
+            let value = ArgValue::Text(item.to_string());
+            set_value(&mut model, &setting, SettingOperator::Append, value).unwrap();
+        }
+        assert_eq!(
+            model.software.unwrap().patterns.unwrap(),
+            ["base", "a,b", "gnome"]
+        );
+    }
+
+    #[test]
+    fn test_mask_secrets() {
+        let setting = find_setting("iscsi.targets", Some(true)).unwrap();
+        let mut value = serde_json::json!([
+            {"name": "iqn.2023-01.com.example:storage", "password": "s3cr3t"}
+        ]);
+        mask_secrets(&setting, &mut value);
+        assert_eq!(
+            value,
+            serde_json::json!([
+                {"name": "iqn.2023-01.com.example:storage", "password": MASKED_SECRET}
+            ])
+        );
+
+        let setting = find_setting("root.password", None).unwrap();
+        let mut value = serde_json::json!("nots3cr3t");
+        mask_secrets(&setting, &mut value);
+        assert_eq!(value, MASKED_SECRET);
     }
-    Err(Box::new(CliError::InvalidKeyName(key.to_string())))
 }
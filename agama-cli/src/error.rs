@@ -4,6 +4,20 @@ use thiserror::Error;
 pub enum CliError {
     #[error("Invalid key name: '{0}'")]
     InvalidKeyName(String),
+    #[error("Unknown setting '{0}'. Did you mean '{1}'?")]
+    UnknownKey(String, String),
+    #[error("'{0}' is a collection. Use 'config add' or 'config remove' instead")]
+    CollectionKey(String),
+    #[error("'{0}' is not a collection")]
+    NotACollection(String),
+    #[error("Cannot add items to '{0}' as it is not a list or a collection")]
+    NotAList(String),
+    #[error("Cannot ask for '{0}' as it is not a secret")]
+    NotASecret(String),
     #[error("Cannot perform the installation as the settings are not valid")]
     ValidationError,
+    #[error("The installation would delete existing data. Use --allow-destructive to proceed")]
+    DestructiveInstallation,
+    #[error("The profile is not valid")]
+    InvalidProfile,
 }
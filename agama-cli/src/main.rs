@@ -6,18 +6,25 @@ mod error;
 mod printers;
 mod profile;
 mod progress;
+mod storage;
 
 use crate::error::CliError;
 use async_std::task::{self, block_on};
 use commands::Commands;
-use config::run as run_config_cmd;
+use config::{load_settings, run as run_config_cmd};
+use agama_lib::cmdline::KernelCmdline;
 use agama_lib::error::ServiceError;
+use agama_lib::layers::{LayeredSettings, Origin};
 use agama_lib::manager::ManagerClient;
 use agama_lib::progress::build_progress_monitor;
+use agama_lib::storage::StorageClient;
+use agama_lib::Store as SettingsStore;
 use printers::Format;
 use progress::InstallerProgress;
-use profile::run as run_profile_cmd;
+use profile::{fetch_profile, run as run_profile_cmd};
+use storage::run as run_storage_cmd;
 use std::error::Error;
+use std::path::Path;
 use std::time::Duration;
 
 #[derive(Parser)]
@@ -26,9 +33,9 @@ struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 
-    /// Format output
-    #[arg(value_enum, short, long, default_value_t = Format::Json)]
-    pub format: Format,
+    /// Format output (JSON by default)
+    #[arg(value_enum, short, long)]
+    pub format: Option<Format>,
 }
 
 async fn probe() -> Result<(), Box<dyn Error>> {
@@ -39,12 +46,18 @@ async fn probe() -> Result<(), Box<dyn Error>> {
     Ok(probe.await?)
 }
 
-async fn install(manager: &ManagerClient<'_>) -> Result<(), Box<dyn Error>> {
+async fn install(
+    manager: &ManagerClient<'_>,
+    allow_destructive: bool,
+) -> Result<(), Box<dyn Error>> {
     if !manager.can_install().await? {
         // TODO: add some hints what is wrong or add dedicated command for it?
         eprintln!("There are issues with configuration. Cannot install.");
         return Err(Box::new(CliError::ValidationError));
     }
+    if !allow_destructive {
+        check_destructive_actions().await?;
+    }
     let another_manager = build_manager().await?;
     let install = task::spawn(async move { another_manager.install().await });
     show_progress().await?;
@@ -52,6 +65,52 @@ async fn install(manager: &ManagerClient<'_>) -> Result<(), Box<dyn Error>> {
     Ok(install.await?)
 }
 
+/// Fails if the storage proposal deletes any device, listing the affected ones
+async fn check_destructive_actions() -> Result<(), Box<dyn Error>> {
+    let storage = StorageClient::new(agama_lib::connection().await?).await?;
+    let deletions: Vec<_> = storage
+        .actions()
+        .await?
+        .into_iter()
+        .filter(|a| a.delete)
+        .collect();
+    if deletions.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("The following actions would delete existing data:");
+    for action in deletions {
+        eprintln!("  {}", action.text);
+    }
+    Err(Box::new(CliError::DestructiveInstallation))
+}
+
+/// Runs the autoinstallation using the parameters from the kernel command line
+async fn auto(
+    manager: &ManagerClient<'_>,
+    cmdline: &Path,
+    allow_destructive: bool,
+) -> Result<(), Box<dyn Error>> {
+    let cmdline = KernelCmdline::read(cmdline)?;
+    if cmdline.is_empty() {
+        eprintln!("No autoinstallation parameters in the kernel command line.");
+        return Ok(());
+    }
+
+    let mut layers = LayeredSettings::default();
+    if let Some(url) = &cmdline.profile_url {
+        layers.add_layer(&fetch_profile(url)?, Origin::Profile, None);
+    }
+    layers.add_layer(&cmdline.settings()?, Origin::Cmdline, None);
+    let store = SettingsStore::new(agama_lib::connection().await?).await?;
+    load_settings(&store, layers, None).await?;
+
+    if cmdline.install {
+        install(manager, allow_destructive).await?;
+    }
+    Ok(())
+}
+
 async fn show_progress() -> Result<(), ServiceError> {
     // wait 1 second to give other task chance to start, so progress can display something
     task::sleep(Duration::from_secs(1)).await;
@@ -80,8 +139,10 @@ async fn build_manager<'a>() -> Result<ManagerClient<'a>, Box<dyn Error>> {
 async fn run_command(cli: Cli) -> Result<(), Box<dyn Error>> {
     match cli.command {
         Commands::Config(subcommand) => {
-            let manager = build_manager().await?;
-            block_on(wait_for_services(&manager))?;
+            if subcommand.needs_service() {
+                let manager = build_manager().await?;
+                block_on(wait_for_services(&manager))?;
+            }
             block_on(run_config_cmd(subcommand, cli.format))
         }
         Commands::Probe => {
@@ -89,11 +150,24 @@ async fn run_command(cli: Cli) -> Result<(), Box<dyn Error>> {
             block_on(wait_for_services(&manager))?;
             block_on(probe())
         }
-        Commands::Profile(subcommand) => Ok(run_profile_cmd(subcommand)?),
-        Commands::Install => {
+        Commands::Profile(subcommand) => run_profile_cmd(subcommand, cli.format).await,
+        Commands::Storage(subcommand) => {
+            let manager = build_manager().await?;
+            block_on(wait_for_services(&manager))?;
+            run_storage_cmd(subcommand, cli.format).await
+        }
+        Commands::Install { allow_destructive } => {
+            let manager = build_manager().await?;
+            block_on(wait_for_services(&manager))?;
+            block_on(install(&manager, allow_destructive))
+        }
+        Commands::Auto {
+            cmdline,
+            allow_destructive,
+        } => {
             let manager = build_manager().await?;
             block_on(wait_for_services(&manager))?;
-            block_on(install(&manager))
+            block_on(auto(&manager, Path::new(&cmdline), allow_destructive))
         }
         _ => unimplemented!(),
     }
@@ -1,24 +1,55 @@
+use crate::config::load_settings;
+use crate::error::CliError;
+use crate::printers::{print, Format};
+use agama_lib::connection;
+use agama_lib::install_settings::InstallSettings;
+use agama_lib::layers::{LayeredSettings, Origin};
+use agama_lib::profile::{
+    autoyast, download, profile_schema, ProfileEvaluator, ProfileFormat, ProfileValidator,
+    ValidationResult,
+};
+use agama_lib::Store as SettingsStore;
 use clap::Subcommand;
-use agama_lib::error::ProfileError;
-use agama_lib::profile::{download, ProfileEvaluator, ProfileValidator, ValidationResult};
-use std::{path::Path};
+use std::{error::Error, io, path::Path};
 
 #[derive(Subcommand, Debug)]
 pub enum ProfileCommands {
     /// Download the profile from a given location
     Download { url: String },
 
-    /// Validate a profile using JSON Schema
+    /// Validate a JSON or YAML profile using JSON Schema
     Validate { path: String },
 
     /// Evaluate a profile, injecting the hardware information from D-Bus
     Evaluate { path: String },
+
+    /// Download, evaluate (if needed), validate and load a JSON, YAML or jsonnet profile
+    Import { url: String },
+
+    /// Print the JSON Schema for the profiles
+    Schema,
+
+    /// Convert an AutoYaST profile into an Agama one, reporting the unsupported sections
+    Convert { path: String },
 }
 
-fn validate(path: String) -> Result<(), ProfileError> {
+fn validate(path: String, format: Option<Format>) -> Result<(), Box<dyn Error>> {
     let validator = ProfileValidator::default_schema()?;
     let path = Path::new(&path);
     let result = validator.validate_file(path)?;
+    let valid = result.is_valid();
+    match format {
+        Some(format @ (Format::Json | Format::Yaml)) => print(result, io::stdout(), format)?,
+        _ => print_validation_result(result),
+    }
+
+    if !valid {
+        return Err(Box::new(CliError::InvalidProfile));
+    }
+    Ok(())
+}
+
+fn print_validation_result(result: ValidationResult) {
     match result {
         ValidationResult::Valid => {
             println!("The profile is valid")
@@ -26,22 +57,95 @@ fn validate(path: String) -> Result<(), ProfileError> {
         ValidationResult::NotValid(errors) => {
             println!("The profile is not valid. Please, check the following errors:\n");
             for error in errors {
-                println!("* {error}")
+                match error.location {
+                    Some(location) => println!(
+                        "* {} (line {}, column {})",
+                        error.message, location.line, location.column
+                    ),
+                    None => println!("* {}", error.message),
+                }
             }
         }
     }
-    Ok(())
 }
 
-fn evaluate(path: String) -> Result<(), ProfileError> {
+fn evaluate(path: String, format: Option<Format>) -> Result<(), Box<dyn Error>> {
     let evaluator = ProfileEvaluator {};
-    evaluator.evaluate(Path::new(&path))
+    let path = Path::new(&path);
+    match format {
+        Some(Format::Yaml) => {
+            let mut output = vec![];
+            evaluator.evaluate(path, &mut output)?;
+            let profile: serde_json::Value = serde_json::from_slice(&output)?;
+            print(profile, io::stdout(), Format::Yaml)
+        }
+        _ => Ok(evaluator.evaluate(path, io::stdout())?),
+    }
+}
+
+fn convert(path: String, format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let conversion = autoyast::convert(&contents)?;
+    print(
+        conversion.settings.to_profile(),
+        io::stdout(),
+        format.unwrap_or(Format::Json),
+    )?;
+
+    if !conversion.dropped.is_empty() {
+        eprintln!("The following AutoYaST sections are not supported and were dropped:");
+        for path in conversion.dropped {
+            eprintln!("* {path}");
+        }
+    }
+    Ok(())
 }
 
-pub fn run(subcommand: ProfileCommands) -> Result<(), ProfileError> {
+/// Downloads, evaluates (if needed) and validates a profile
+pub fn fetch_profile(url: &str) -> Result<InstallSettings, Box<dyn Error>> {
+    let mut contents = vec![];
+    download(url, &mut contents)?;
+    let mut contents = String::from_utf8(contents)?;
+
+    let mut format = ProfileFormat::detect(Path::new(url), &contents);
+    if format == ProfileFormat::Jsonnet {
+        let mut output = vec![];
+        ProfileEvaluator {}.evaluate_str(&contents, &mut output)?;
+        contents = String::from_utf8(output)?;
+        format = ProfileFormat::Json;
+    }
+
+    let validator = ProfileValidator::default_schema()?;
+    let result = validator.validate_with_format(&contents, format)?;
+    if !result.is_valid() {
+        print_validation_result(result);
+        return Err(Box::new(CliError::InvalidProfile));
+    }
+
+    Ok(format.parse(&contents)?)
+}
+
+async fn import(url: String) -> Result<(), Box<dyn Error>> {
+    let settings = fetch_profile(&url)?;
+    let mut layers = LayeredSettings::default();
+    layers.add_layer(&settings, Origin::Profile, None);
+    let store = SettingsStore::new(connection().await?).await?;
+    load_settings(&store, layers, None).await
+}
+
+pub async fn run(
+    subcommand: ProfileCommands,
+    format: Option<Format>,
+) -> Result<(), Box<dyn Error>> {
     match subcommand {
-        ProfileCommands::Download { url } => download(&url),
-        ProfileCommands::Validate { path } => validate(path),
-        ProfileCommands::Evaluate { path } => evaluate(path),
+        ProfileCommands::Download { url } => Ok(download(&url, io::stdout())?),
+        ProfileCommands::Validate { path } => validate(path, format),
+        ProfileCommands::Evaluate { path } => evaluate(path, format),
+        ProfileCommands::Import { url } => import(url).await,
+        ProfileCommands::Schema => {
+            let format = format.unwrap_or(Format::Json);
+            print(profile_schema(), io::stdout(), format)
+        }
+        ProfileCommands::Convert { path } => convert(path, format),
     }
 }
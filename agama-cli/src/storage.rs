@@ -0,0 +1,253 @@
+use crate::config::prompt_secret;
+use crate::printers::{print, Format};
+use agama_lib::connection;
+use agama_lib::settings::{ConversionError, SettingValue, ValueSource};
+use agama_lib::storage::{
+    IscsiAuth, IscsiClient, IscsiNode, IscsiStartup, StorageClient, StorageDevice,
+    DEFAULT_ISCSI_PORT,
+};
+use clap::{Args, Subcommand};
+use std::{error::Error, io};
+
+#[derive(Subcommand, Debug)]
+pub enum StorageCommands {
+    /// Lists the devices which can be used to install the system, including their partitions
+    ///
+    /// Use --format to get the devices as JSON or YAML.
+    Devices,
+    /// Lists the actions that the installation performs in the storage devices
+    ///
+    /// The actions which delete data are marked with an exclamation mark.
+    Actions,
+    /// Handles the iSCSI targets
+    #[command(subcommand)]
+    Iscsi(IscsiCommands),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IscsiCommands {
+    /// Lists the known iSCSI targets
+    List,
+    /// Discovers the targets offered by an iSCSI portal
+    Discover {
+        /// Portal address
+        address: String,
+        /// Portal port
+        #[arg(long, default_value_t = DEFAULT_ISCSI_PORT)]
+        port: u32,
+        #[command(flatten)]
+        auth: AuthArgs,
+    },
+    /// Logs in a discovered target
+    Login {
+        /// Target name (e.g., iqn.2023-01.com.example:storage)
+        target: String,
+        #[command(flatten)]
+        portal: PortalArgs,
+        /// When to log in the target on the installed system (onboot, manual or automatic)
+        #[arg(long, default_value = "onboot", value_parser = parse_startup)]
+        startup: IscsiStartup,
+        #[command(flatten)]
+        auth: AuthArgs,
+    },
+    /// Logs out a target
+    Logout {
+        /// Target name (e.g., iqn.2023-01.com.example:storage)
+        target: String,
+        #[command(flatten)]
+        portal: PortalArgs,
+    },
+}
+
+/// Portal to tell apart the targets with the same name
+#[derive(Args, Debug)]
+pub struct PortalArgs {
+    /// Portal address
+    #[arg(long)]
+    address: Option<String>,
+    /// Portal port
+    #[arg(long)]
+    port: Option<u32>,
+}
+
+/// CHAP credentials
+///
+/// The passwords can be read from a prompt (@prompt), a file (@file:path) or an environment
+/// variable (@env:NAME).
+#[derive(Args, Debug)]
+pub struct AuthArgs {
+    /// CHAP user name
+    #[arg(long)]
+    username: Option<String>,
+    /// CHAP password
+    #[arg(long)]
+    password: Option<String>,
+    /// User name to authenticate the target (mutual CHAP)
+    #[arg(long)]
+    reverse_username: Option<String>,
+    /// Password to authenticate the target (mutual CHAP)
+    #[arg(long)]
+    reverse_password: Option<String>,
+}
+
+fn parse_startup(value: &str) -> Result<IscsiStartup, ConversionError> {
+    SettingValue(value.to_string()).try_into()
+}
+
+impl AuthArgs {
+    fn into_auth(self) -> Result<IscsiAuth, Box<dyn Error>> {
+        let secret = |key: &str, value: Option<String>| -> Result<_, Box<dyn Error>> {
+            match value.as_deref().and_then(ValueSource::secret_reference) {
+                Some(source) => Ok(Some(source.read(key, io::empty(), prompt_secret)?)),
+                None => Ok(value),
+            }
+        };
+        Ok(IscsiAuth {
+            username: self.username,
+            password: secret("password", self.password)?,
+            reverse_username: self.reverse_username,
+            reverse_password: secret("reverse password", self.reverse_password)?,
+        })
+    }
+}
+
+async fn devices(format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let client = StorageClient::new(connection().await?).await?;
+    let devices = client.available_devices().await?;
+    match format {
+        Some(format @ (Format::Json | Format::Yaml)) => print(devices, io::stdout(), format),
+        _ => {
+            for device in &devices {
+                print_device(device, "", "");
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn actions(format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let client = StorageClient::new(connection().await?).await?;
+    let actions = client.actions().await?;
+    match format {
+        Some(format @ (Format::Json | Format::Yaml)) => print(actions, io::stdout(), format),
+        _ => {
+            for action in &actions {
+                let mark = if action.delete { "!" } else { " " };
+                println!("{mark} {}", action.text);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn iscsi(subcommand: IscsiCommands, format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let client = IscsiClient::new(connection().await?).await?;
+    match subcommand {
+        IscsiCommands::List => print_nodes(client.nodes().await?, format),
+        IscsiCommands::Discover {
+            address,
+            port,
+            auth,
+        } => {
+            client.discover(&address, port, &auth.into_auth()?).await?;
+            let nodes = client
+                .nodes()
+                .await?
+                .into_iter()
+                .filter(|n| n.address == address && n.port == port)
+                .collect();
+            print_nodes(nodes, format)
+        }
+        IscsiCommands::Login {
+            target,
+            portal,
+            startup,
+            auth,
+        } => {
+            let node = client
+                .find_node(&target, portal.address.as_deref(), portal.port)
+                .await?;
+            Ok(client.login(&node, &auth.into_auth()?, startup).await?)
+        }
+        IscsiCommands::Logout { target, portal } => {
+            let node = client
+                .find_node(&target, portal.address.as_deref(), portal.port)
+                .await?;
+            Ok(client.logout(&node).await?)
+        }
+    }
+}
+
+/// Prints one node per line, including its portal and whether it is connected
+fn print_nodes(nodes: Vec<IscsiNode>, format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    if let Some(format @ (Format::Json | Format::Yaml)) = format {
+        return print(nodes, io::stdout(), format);
+    }
+    for node in &nodes {
+        let mut details = vec![];
+        if node.connected {
+            details.push(format!("connected, {}", node.startup));
+        }
+        if node.ibft {
+            details.push("iBFT".to_string());
+        }
+        let details = if details.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", details.join(", "))
+        };
+        println!("{} {}:{}{details}", node.target, node.address, node.port);
+    }
+    Ok(())
+}
+
+/// Prints a device and, below it, its partitions as a tree
+///
+/// * `prefix`: text to print before the device.
+/// * `indent`: text to print before the partitions.
+fn print_device(device: &StorageDevice, prefix: &str, indent: &str) {
+    let size = device.size.map(|s| s.to_string());
+    let model = [&device.vendor, &device.model]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let details: Vec<&str> = [
+        size.as_deref(),
+        device.device_type.as_deref(),
+        Some(model.as_str()).filter(|m| !m.is_empty()),
+        device.transport.as_deref(),
+        device.filesystem.as_deref(),
+        device.mount_point.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    println!("{prefix}{} ({})", device.name, details.join(", "));
+
+    let count = device.partitions.len();
+    for (index, partition) in device.partitions.iter().enumerate() {
+        let (branch, next) = if index + 1 == count {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        print_device(
+            partition,
+            &format!("{indent}{branch}"),
+            &format!("{indent}{next}"),
+        );
+    }
+}
+
+pub async fn run(
+    subcommand: StorageCommands,
+    format: Option<Format>,
+) -> Result<(), Box<dyn Error>> {
+    match subcommand {
+        StorageCommands::Devices => devices(format).await,
+        StorageCommands::Actions => actions(format).await,
+        StorageCommands::Iscsi(subcommand) => iscsi(subcommand, format).await,
+    }
+}
@@ -1,11 +1,34 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Fields, Ident};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Attribute, DeriveInput, Field, Fields, GenericArgument, Ident, Lit, Meta,
+    NestedMeta, PathArguments, Type,
+};
 
 /// Derive Settings, typically for a FooSettings struct.
 /// (see dinstaller_lib::settings::Settings but I cannot link to it without a circular dependency)
-#[proc_macro_derive(Settings, attributes(collection_setting))]
+///
+/// The fields are handled according to their attributes:
+///
+/// * `#[collection_setting]`: a collection (`Vec<T>`) whose elements are added or removed.
+/// * `#[settings(nested)]`: an `Option<T>` holding a section (e.g., `"software"` for
+///   `"software.product"`).
+/// * `#[settings(flatten)]`: an `Option<T>` whose settings are exposed directly. Only one field
+///   can be flattened.
+/// * Otherwise, a scalar (`Option<T>`).
+///
+/// Additionally, `#[settings(alias = "name")]` adds an alternative name to a scalar or a
+/// collection, `#[settings(secret)]` marks a scalar as a secret (e.g., a password),
+/// `#[settings(secret_fields = "password,reverse_password")]` marks some fields of the elements
+/// of a collection as secrets and `#[settings(scope = "users")]` sets the scope of a section. The name of the field can be
+/// changed with `#[serde(rename = "name")]`.
+///
+/// When merging, the collections are replaced by default. Use `#[settings(merge = "append")]` or
+/// `#[settings(merge = "merge-by-key")]` to change the default strategy and
+/// `#[settings(merge_key = "name")]` to set the field which identifies the elements (or a method,
+/// e.g., `merge_key = "key()"`). Without a key, the elements are compared as a whole.
+#[proc_macro_derive(Settings, attributes(collection_setting, settings))]
 pub fn agama_attributes_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let fields = match &input.data {
@@ -16,29 +39,26 @@ pub fn agama_attributes_derive(input: TokenStream) -> TokenStream {
         _ => panic!("only structs are supported"),
     };
 
-    let (collection, scalar): (Vec<_>, Vec<_>) = fields.clone().into_iter().partition(|f| {
-        f.attrs
-            .iter()
-            .any(|a| a.path.is_ident("collection_setting"))
-    });
+    let fields: Vec<SettingField> = fields.iter().map(SettingField::from).collect();
+    let settings = SettingFields::new(fields);
 
-    let scalar_field_names: Vec<Ident> =
-        scalar.into_iter().filter_map(|field| field.ident).collect();
-
-    let set_fn = expand_set_fn(&scalar_field_names);
-    let merge_fn = expand_merge_fn(&scalar_field_names);
-
-    let collection_field_names: Vec<Ident> = collection
-        .into_iter()
-        .filter_map(|field| field.ident)
-        .collect();
-    let add_fn = expand_add_fn(&collection_field_names);
+    let settings_fn = expand_settings_fn(&settings);
+    let get_fn = expand_get_fn(&settings);
+    let set_fn = expand_set_fn(&settings);
+    let unset_fn = expand_unset_fn(&settings);
+    let add_fn = expand_add_fn(&settings);
+    let remove_fn = expand_remove_fn(&settings);
+    let merge_fn = expand_merge_fn(&settings);
 
     let name = input.ident;
     let expanded = quote! {
         impl Settings for #name {
+            #settings_fn
+            #get_fn
             #set_fn
+            #unset_fn
             #add_fn
+            #remove_fn
             #merge_fn
         }
     };
@@ -46,51 +66,720 @@ pub fn agama_attributes_derive(input: TokenStream) -> TokenStream {
     expanded.into()
 }
 
-fn expand_set_fn(field_name: &Vec<Ident>) -> TokenStream2 {
-    if field_name.is_empty() {
+/// Derive the conversion from a SettingValue for enums whose variants have no fields.
+///
+/// The value is compared, case-insensitively, with the snake_case name of each variant (e.g.,
+/// "pbkdf2" for `Pbkdf2`). The name can be changed with `#[serde(rename = "name")]`. The enum
+/// is displayed using the same names.
+#[proc_macro_derive(SettingEnum)]
+pub fn agama_setting_enum_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let variants = match &input.data {
+        syn::Data::Enum(data) => &data.variants,
+        _ => panic!("only enums are supported"),
+    };
+
+    let idents: Vec<&Ident> = variants
+        .iter()
+        .map(|variant| {
+            if !variant.fields.is_empty() {
+                panic!("only variants without fields are supported");
+            }
+            &variant.ident
+        })
+        .collect();
+    let names: Vec<String> = variants
+        .iter()
+        .map(|variant| {
+            serde_rename(&variant.attrs)
+                .unwrap_or_else(|| to_snake_case(&variant.ident.to_string()))
+                .to_lowercase()
+        })
+        .collect();
+    let reason = format!("expected one of {}", names.join(", "));
+
+    let name = input.ident;
+    let expanded = quote! {
+        impl TryFrom<SettingValue> for #name {
+            type Error = ConversionError;
+
+            fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+                match value.0.to_lowercase().as_str() {
+                    #(#names => Ok(Self::#idents),)*
+                    _ => Err(ConversionError::new(#reason, &value)),
+                }
+            }
+        }
+
+        impl TryFrom<SettingValue> for Option<#name> {
+            type Error = ConversionError;
+
+            fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+                Ok(Some(value.try_into()?))
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let name = match self {
+                    #(Self::#idents => #names,)*
+                };
+                write!(f, "{}", name)
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+#[derive(PartialEq)]
+enum FieldKind {
+    Scalar,
+    Collection,
+    Nested,
+    Flatten,
+}
+
+/// Field of a struct which derives Settings
+struct SettingField {
+    ident: Ident,
+    /// Name used in the attributes (e.g., "user_name")
+    name: String,
+    aliases: Vec<String>,
+    kind: FieldKind,
+    secret: bool,
+    /// Fields of the elements of a collection which are secrets (e.g., "reverse_password")
+    secret_fields: Vec<String>,
+    scope: Option<Ident>,
+    /// Default merge strategy for collections (e.g., `MergeByKey`)
+    merge: Option<Ident>,
+    /// Field or method which identifies the elements of a collection when merging
+    merge_key: Option<TokenStream2>,
+    ty: Type,
+    description: String,
+}
+
+impl From<&Field> for SettingField {
+    fn from(field: &Field) -> Self {
+        let ident = field
+            .ident
+            .clone()
+            .expect("only named fields are supported");
+        let mut setting = SettingField {
+            name: serde_rename(&field.attrs).unwrap_or_else(|| ident.to_string()),
+            ident,
+            aliases: vec![],
+            kind: FieldKind::Scalar,
+            secret: false,
+            secret_fields: vec![],
+            scope: None,
+            merge: None,
+            merge_key: None,
+            ty: field.ty.clone(),
+            description: doc_comment(field),
+        };
+
+        if field
+            .attrs
+            .iter()
+            .any(|a| a.path.is_ident("collection_setting"))
+        {
+            setting.kind = FieldKind::Collection;
+        }
+
+        for meta in nested_meta(&field.attrs, "settings") {
+            match meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nested") => {
+                    setting.kind = FieldKind::Nested
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                    setting.kind = FieldKind::Flatten
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("secret") => {
+                    setting.secret = true
+                }
+                NestedMeta::Meta(Meta::NameValue(meta)) => match (meta.lit, &meta.path) {
+                    (Lit::Str(alias), path) if path.is_ident("alias") => {
+                        setting.aliases.push(alias.value())
+                    }
+                    (Lit::Str(scope), path) if path.is_ident("scope") => {
+                        setting.scope = Some(format_ident!("{}", to_pascal_case(&scope.value())))
+                    }
+                    (Lit::Str(merge), path) if path.is_ident("merge") => {
+                        let strategy = match merge.value().as_str() {
+                            "replace" => "Replace",
+                            "append" => "Append",
+                            "merge-by-key" => "MergeByKey",
+                            _ => panic!("unknown merge strategy"),
+                        };
+                        setting.merge = Some(format_ident!("{}", strategy))
+                    }
+                    (Lit::Str(fields), path) if path.is_ident("secret_fields") => setting
+                        .secret_fields
+                        .extend(fields.value().split(',').map(|f| f.trim().to_string())),
+                    (Lit::Str(key), path) if path.is_ident("merge_key") => {
+                        let key = key.value().parse().expect("not a valid merge key");
+                        setting.merge_key = Some(key)
+                    }
+                    _ => panic!("unknown settings attribute"),
+                },
+                _ => panic!("unknown settings attribute"),
+            }
+        }
+
+        let is_section = matches!(setting.kind, FieldKind::Nested | FieldKind::Flatten);
+        if is_section && !setting.aliases.is_empty() {
+            panic!("aliases are only supported for scalars and collections");
+        }
+        if !is_section && setting.scope.is_some() {
+            panic!("the scope can only be set for nested and flattened fields");
+        }
+        if setting.secret && setting.kind != FieldKind::Scalar {
+            panic!("only scalars can be secrets");
+        }
+        let is_collection = setting.kind == FieldKind::Collection;
+        if !is_collection && !setting.secret_fields.is_empty() {
+            panic!("the secret fields are only supported for collections");
+        }
+        if !is_collection && (setting.merge.is_some() || setting.merge_key.is_some()) {
+            panic!("the merge options are only supported for collections");
+        }
+        setting
+    }
+}
+
+impl SettingField {
+    /// Match pattern for the field name and its aliases (e.g., `"ssh_public_key" | "ssh_key"`)
+    fn pattern(&self) -> TokenStream2 {
+        let names = std::iter::once(&self.name).chain(&self.aliases);
+        quote! { #(#names)|* }
+    }
+
+    /// Key used in the profiles (e.g., "userName")
+    fn key(&self) -> String {
+        to_camel_case(&self.name)
+    }
+
+    /// Type of the section for nested and flattened fields (`T` in `Option<T>`)
+    fn section_type(&self) -> &Type {
+        option_type(&self.ty).expect("nested and flattened fields must be an Option")
+    }
+
+    /// Expression to access the section
+    fn section(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        quote! { self.#ident.get_or_insert(Default::default()) }
+    }
+
+    /// Expression to get a value from the section, even if it is not defined
+    fn get_from_section(&self, attr: TokenStream2) -> TokenStream2 {
+        let ident = &self.ident;
+        let ty = self.section_type();
+        quote! {
+            match &self.#ident {
+                Some(section) => section.get(#attr),
+                None => <#ty>::default().get(#attr),
+            }
+        }
+    }
+}
+
+/// Fields of a struct, classified by kind
+struct SettingFields {
+    all: Vec<SettingField>,
+}
+
+impl SettingFields {
+    fn new(all: Vec<SettingField>) -> Self {
+        let flattened = all.iter().filter(|f| f.kind == FieldKind::Flatten).count();
+        if flattened > 1 {
+            panic!("only one field can be flattened");
+        }
+        Self { all }
+    }
+
+    fn of_kind(&self, kind: FieldKind) -> Vec<&SettingField> {
+        self.all.iter().filter(|f| f.kind == kind).collect()
+    }
+
+    fn flattened(&self) -> Option<&SettingField> {
+        self.all.iter().find(|f| f.kind == FieldKind::Flatten)
+    }
+
+    /// Dispatches a call to the nested sections, removing the section name from the attribute
+    fn nested_dispatch(&self, call: impl Fn(&SettingField) -> TokenStream2) -> TokenStream2 {
+        let nested = self.of_kind(FieldKind::Nested);
+        if nested.is_empty() {
+            return quote! {};
+        }
+
+        let patterns = nested.iter().map(|f| f.pattern());
+        let calls = nested.iter().map(|f| call(f));
+        let keys = nested.iter().map(|f| f.key());
+        quote! {
+            if let Some((ns, id)) = attr.split_once('.') {
+                match ns {
+                    #(#patterns => return #calls.map_err(|e| e.in_section(#keys)),)*
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Expression to evaluate when no field matches, passing the call to the flattened field
+    ///
+    /// * `error`: SettingsError variant to return if there is no flattened field.
+    fn fallback(&self, call: impl Fn(&SettingField) -> TokenStream2, error: &str) -> TokenStream2 {
+        let error = format_ident!("{}", error);
+        match self.flattened() {
+            Some(field) => call(field),
+            None => quote! { Err(SettingsError::#error(attr.to_string())) },
+        }
+    }
+
+    /// Whether a function is needed for the given field kinds
+    fn needs(&self, kinds: &[FieldKind]) -> bool {
+        self.all.iter().any(|f| {
+            matches!(f.kind, FieldKind::Nested | FieldKind::Flatten) || kinds.contains(&f.kind)
+        })
+    }
+}
+
+fn expand_settings_fn(fields: &SettingFields) -> TokenStream2 {
+    let descriptions = fields.all.iter().map(|field| {
+        let scope = match &field.scope {
+            Some(scope) => quote! { Some(Scope::#scope) },
+            None => quote! { None },
+        };
+
+        match field.kind {
+            FieldKind::Nested => {
+                let ty = field.section_type();
+                let key = to_camel_case(&field.name);
+                let attr = &field.name;
+                quote! {
+                    settings.extend(
+                        <#ty>::settings()
+                            .into_iter()
+                            .map(|s| s.in_section(#key, #attr, #scope))
+                    );
+                }
+            }
+            FieldKind::Flatten => {
+                let ty = field.section_type();
+                quote! {
+                    settings.extend(
+                        <#ty>::settings()
+                            .into_iter()
+                            .map(|s| s.with_scope(#scope))
+                    );
+                }
+            }
+            FieldKind::Scalar | FieldKind::Collection => {
+                let attr = &field.name;
+                let key = to_camel_case(attr);
+                let aliases = field.aliases.iter().map(|a| to_camel_case(a));
+                let collection = field.kind == FieldKind::Collection;
+                let value_type = if collection {
+                    "object".to_string()
+                } else {
+                    type_name(&field.ty)
+                };
+                let description = &field.description;
+                let secret = field.secret;
+                let secret_fields = field.secret_fields.iter().map(|f| to_camel_case(f));
+                quote! {
+                    settings.push(SettingDescription {
+                        key: #key.to_string(),
+                        attr: #attr.to_string(),
+                        aliases: vec![#(#aliases.to_string()),*],
+                        value_type: #value_type.to_string(),
+                        description: #description.to_string(),
+                        collection: #collection,
+                        secret: #secret,
+                        secret_fields: vec![#(#secret_fields.to_string()),*],
+                        scope: None,
+                    });
+                }
+            }
+        }
+    });
+
+    quote! {
+        fn settings() -> Vec<SettingDescription> {
+            let mut settings = vec![];
+            #(#descriptions)*
+            settings
+        }
+    }
+}
+
+fn expand_get_fn(fields: &SettingFields) -> TokenStream2 {
+    if !fields.needs(&[FieldKind::Scalar, FieldKind::Collection]) {
+        return quote! {};
+    }
+
+    let nested = fields.nested_dispatch(|f| f.get_from_section(quote! { id }));
+    let fallback = fields.fallback(|f| f.get_from_section(quote! { attr }), "UnknownKey");
+    let values: Vec<_> = fields
+        .all
+        .iter()
+        .filter(|f| matches!(f.kind, FieldKind::Scalar | FieldKind::Collection))
+        .collect();
+    let patterns = values.iter().map(|f| f.pattern());
+    let idents = values.iter().map(|f| &f.ident);
+
+    // the settings types can always be represented as JSON
+    quote! {
+        fn get(&self, attr: &str) -> Result<serde_json::Value, SettingsError> {
+            #nested
+            match attr {
+                #(#patterns => Ok(serde_json::to_value(&self.#idents).unwrap_or_default()),)*
+                _ => #fallback
+            }
+        }
+    }
+}
+
+fn expand_set_fn(fields: &SettingFields) -> TokenStream2 {
+    if !fields.needs(&[FieldKind::Scalar]) {
+        return quote! {};
+    }
+
+    let nested = fields.nested_dispatch(|f| {
+        let section = f.section();
+        quote! { #section.set(id, value) }
+    });
+    let fallback = fields.fallback(
+        |f| {
+            let section = f.section();
+            quote! { #section.set(attr, value) }
+        },
+        "UnknownKey",
+    );
+    let scalars = fields.of_kind(FieldKind::Scalar);
+    let patterns = scalars.iter().map(|f| f.pattern());
+    let idents = scalars.iter().map(|f| &f.ident);
+    let keys = scalars.iter().map(|f| f.key());
+
+    quote! {
+        fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), SettingsError> {
+            #nested
+            match attr {
+                #(#patterns => {
+                    self.#idents = value
+                        .try_into()
+                        .map_err(|e| SettingsError::from(e).in_section(#keys))?;
+                    Ok(())
+                })*
+                _ => #fallback
+            }
+        }
+    }
+}
+
+fn expand_unset_fn(fields: &SettingFields) -> TokenStream2 {
+    if !fields.needs(&[FieldKind::Scalar, FieldKind::Collection]) {
+        return quote! {};
+    }
+
+    let nested = fields.nested_dispatch(|f| {
+        let section = f.section();
+        quote! { #section.unset(id) }
+    });
+    let fallback = fields.fallback(
+        |f| {
+            let section = f.section();
+            quote! { #section.unset(attr) }
+        },
+        "UnknownKey",
+    );
+    let scalars = fields.of_kind(FieldKind::Scalar);
+    let scalar_patterns = scalars.iter().map(|f| f.pattern());
+    let scalar_idents = scalars.iter().map(|f| &f.ident);
+    let collections = fields.of_kind(FieldKind::Collection);
+    let collection_patterns = collections.iter().map(|f| f.pattern());
+    let collection_idents = collections.iter().map(|f| &f.ident);
+
+    quote! {
+        fn unset(&mut self, attr: &str) -> Result<(), SettingsError> {
+            #nested
+            match attr {
+                #(#scalar_patterns => {
+                    self.#scalar_idents = None;
+                    Ok(())
+                })*
+                #(#collection_patterns => {
+                    self.#collection_idents.clear();
+                    Ok(())
+                })*
+                _ => #fallback
+            }
+        }
+    }
+}
+
+fn expand_add_fn(fields: &SettingFields) -> TokenStream2 {
+    if !fields.needs(&[FieldKind::Collection]) {
+        return quote! {};
+    }
+
+    let nested = fields.nested_dispatch(|f| {
+        let section = f.section();
+        quote! { #section.add(id, value) }
+    });
+    let fallback = fields.fallback(
+        |f| {
+            let section = f.section();
+            quote! { #section.add(attr, value) }
+        },
+        "UnknownCollection",
+    );
+    let collections = fields.of_kind(FieldKind::Collection);
+    let patterns = collections.iter().map(|f| f.pattern());
+    let idents = collections.iter().map(|f| &f.ident);
+    let keys = collections.iter().map(|f| f.key());
+
+    quote! {
+        fn add(&mut self, attr: &str, value: SettingObject) -> Result<(), SettingsError> {
+            #nested
+            match attr {
+                #(#patterns => {
+                    let element = value
+                        .try_into()
+                        .map_err(|e| SettingsError::from(e).in_section(#keys))?;
+                    self.#idents.push(element);
+                    Ok(())
+                })*
+                _ => #fallback
+            }
+        }
+    }
+}
+
+fn expand_remove_fn(fields: &SettingFields) -> TokenStream2 {
+    if !fields.needs(&[FieldKind::Collection]) {
         return quote! {};
     }
 
+    let nested = fields.nested_dispatch(|f| {
+        let section = f.section();
+        quote! { #section.remove(id, value) }
+    });
+    let fallback = fields.fallback(
+        |f| {
+            let section = f.section();
+            quote! { #section.remove(attr, value) }
+        },
+        "UnknownCollection",
+    );
+    let collections = fields.of_kind(FieldKind::Collection);
+    let patterns = collections.iter().map(|f| f.pattern());
+    let idents = collections.iter().map(|f| &f.ident);
+    let keys = collections.iter().map(|f| f.key());
+
     quote! {
-        fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), &'static str> {
+        fn remove(&mut self, attr: &str, value: SettingObject) -> Result<(), SettingsError> {
+            #nested
             match attr {
-                #(stringify!(#field_name) => self.#field_name = value.try_into()?,)*
-                _ => return Err("unknown attribute")
-            };
-            Ok(())
+                #(#patterns => {
+                    let element = value
+                        .try_into()
+                        .map_err(|e| SettingsError::from(e).in_section(#keys))?;
+                    let size = self.#idents.len();
+                    self.#idents.retain(|e| e != &element);
+                    if self.#idents.len() == size {
+                        return Err(SettingsError::ElementNotFound(#keys.to_string()));
+                    }
+                    Ok(())
+                })*
+                _ => #fallback
+            }
         }
     }
 }
 
-fn expand_merge_fn(field_name: &Vec<Ident>) -> TokenStream2 {
-    if field_name.is_empty() {
+fn expand_merge_fn(fields: &SettingFields) -> TokenStream2 {
+    if !fields.needs(&[FieldKind::Scalar, FieldKind::Collection]) {
         return quote! {};
     }
 
+    let scalars = fields.of_kind(FieldKind::Scalar);
+    let scalar_idents = scalars.iter().map(|f| &f.ident);
+    let sections: Vec<_> = fields
+        .all
+        .iter()
+        .filter(|f| matches!(f.kind, FieldKind::Nested | FieldKind::Flatten))
+        .collect();
+    let section_idents = sections.iter().map(|f| &f.ident);
+    let collections = fields.of_kind(FieldKind::Collection);
+    let collection_merges = collections.iter().map(|f| expand_collection_merge(f));
+
+    // avoid an unused variable warning when there is nothing to pass the strategy to
+    let strategy = if sections.is_empty() && collections.is_empty() {
+        quote! { _strategy }
+    } else {
+        quote! { strategy }
+    };
+
     quote! {
-        fn merge(&mut self, other: &Self)
+        fn merge_with(&mut self, other: &Self, #strategy: Option<MergeStrategy>)
         where
             Self: Sized,
         {
-            #(if let Some(value) = &other.#field_name {
-                self.#field_name = Some(value.clone())
+            #(if let Some(value) = &other.#scalar_idents {
+                self.#scalar_idents = Some(value.clone())
               })*
+            #(if let Some(other_section) = &other.#section_idents {
+                let section = self.#section_idents.get_or_insert(Default::default());
+                section.merge_with(other_section, strategy);
+              })*
+            #(#collection_merges)*
         }
     }
 }
 
-fn expand_add_fn(field_name: &Vec<Ident>) -> TokenStream2 {
-    if field_name.is_empty() {
-        return quote! {};
-    }
+/// Merges a collection according to the given strategy or to its default one
+fn expand_collection_merge(field: &SettingField) -> TokenStream2 {
+    let ident = &field.ident;
+    let default = field
+        .merge
+        .clone()
+        .unwrap_or_else(|| format_ident!("Replace"));
+    let same_element = match &field.merge_key {
+        Some(key) => quote! { |e| e.#key == element.#key },
+        None => quote! { |e| **e == *element },
+    };
 
     quote! {
-        fn add(&mut self, attr: &str, value: SettingObject) -> Result<(), &'static str> {
-            match attr {
-                #(stringify!(#field_name) => self.#field_name.push(value.try_into()?),)*
-                _ => return Err("unknown attribute")
-            };
-            Ok(())
+        if !other.#ident.is_empty() {
+            match strategy.unwrap_or(MergeStrategy::#default) {
+                MergeStrategy::Replace => self.#ident = other.#ident.clone(),
+                MergeStrategy::Append => self.#ident.extend(other.#ident.iter().cloned()),
+                MergeStrategy::MergeByKey => {
+                    for element in &other.#ident {
+                        match self.#ident.iter_mut().find(#same_element) {
+                            Some(current) => *current = element.clone(),
+                            None => self.#ident.push(element.clone()),
+                        }
+                    }
+                }
+            }
         }
     }
 }
+
+/// Returns the nested meta items of the given attribute (e.g., `nested` in `#[settings(nested)]`)
+fn nested_meta(attrs: &[Attribute], name: &str) -> Vec<NestedMeta> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident(name))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested.into_iter()),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Returns the name set with `#[serde(rename = "...")]`, if any
+fn serde_rename(attrs: &[Attribute]) -> Option<String> {
+    nested_meta(attrs, "serde")
+        .into_iter()
+        .find_map(|meta| match meta {
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("rename") => {
+                match meta.lit {
+                    Lit::Str(name) => Some(name.value()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+}
+
+/// Returns `T` if the given type is an `Option<T>`
+fn option_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Returns the type of a field as shown to the user ("string", "boolean", etc.)
+fn type_name(ty: &Type) -> String {
+    let ty = option_type(ty).unwrap_or(ty);
+    let name = quote!(#ty).to_string().replace(' ', "");
+    match name.as_str() {
+        "String" => "string",
+        "bool" => "boolean",
+        "Vec<String>" => "list",
+        "DiskSize" => "size",
+        "u8" | "u16" | "u32" | "u64" | "i32" | "i64" | "usize" => "integer",
+        // other scalars (e.g., enums) are parsed from strings
+        _ => "string",
+    }
+    .to_string()
+}
+
+/// Joins the lines of the doc comment of a field
+fn doc_comment(field: &Field) -> String {
+    let lines: Vec<String> = field
+        .attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(line) => Some(line.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    lines.join(" ")
+}
+
+/// Converts a field name to the camelCase form used in the profiles (e.g., "user_name" to
+/// "userName")
+fn to_camel_case(name: &str) -> String {
+    let mut parts = name.split('_');
+    let mut camel = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        camel.push_str(&capitalize(part));
+    }
+    camel
+}
+
+/// Converts a variant name to snake_case (e.g., "XfsV5" to "xfs_v5")
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Converts a scope name to the name of the variant (e.g., "users" to "Users")
+fn to_pascal_case(name: &str) -> String {
+    name.split('_').map(capitalize).collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,81 @@
+//! Bootloader configuration support
+
+use super::proxies::BootloaderProxy;
+use crate::error::ServiceError;
+use crate::settings::{ConversionError, SettingValue};
+use agama_derive::SettingEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zbus::Connection;
+
+/// Bootloader to install (grub2 or systemd-boot)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum BootloaderType {
+    Grub2,
+    #[serde(rename = "systemd-boot")]
+    SystemdBoot,
+}
+
+/// Bootloader configuration
+///
+/// The values which are not set are left to the service defaults.
+#[derive(Clone, Debug, Default)]
+pub struct BootloaderConfig {
+    pub bootloader_type: Option<BootloaderType>,
+    /// Time to wait (in seconds) before booting the default entry
+    pub timeout: Option<u32>,
+    /// Extra parameters for the kernel command line (e.g., `"console=ttyS0"`)
+    pub extra_kernel_params: Option<String>,
+    /// Whether to enable the secure boot support
+    pub secure_boot: Option<bool>,
+}
+
+/// D-Bus client for the bootloader configuration
+pub struct BootloaderClient<'a> {
+    bootloader_proxy: BootloaderProxy<'a>,
+}
+
+impl<'a> BootloaderClient<'a> {
+    pub async fn new(connection: Connection) -> Result<BootloaderClient<'a>, ServiceError> {
+        Ok(Self {
+            bootloader_proxy: BootloaderProxy::new(&connection).await?,
+        })
+    }
+
+    /// Returns the current configuration
+    pub async fn config(&self) -> Result<BootloaderConfig, ServiceError> {
+        let bootloader_type = self.bootloader_proxy.bootloader_type().await?;
+        let extra_kernel_params = self.bootloader_proxy.extra_kernel_params().await?;
+        Ok(BootloaderConfig {
+            bootloader_type: SettingValue(bootloader_type).try_into().ok(),
+            timeout: Some(self.bootloader_proxy.timeout().await?),
+            extra_kernel_params: Some(extra_kernel_params).filter(|p| !p.is_empty()),
+            secure_boot: Some(self.bootloader_proxy.secure_boot().await?),
+        })
+    }
+
+    /// Sets the configuration
+    ///
+    /// The whole configuration is replaced, so the missing values get their default ones.
+    pub async fn set_config(&self, config: BootloaderConfig) -> Result<u32, ServiceError> {
+        let mut settings: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+        if let Some(bootloader_type) = config.bootloader_type {
+            settings.insert(
+                "Type",
+                zbus::zvariant::Value::new(bootloader_type.to_string()),
+            );
+        }
+        if let Some(timeout) = config.timeout {
+            settings.insert("Timeout", zbus::zvariant::Value::new(timeout));
+        }
+        if let Some(params) = config.extra_kernel_params {
+            settings.insert("ExtraKernelParams", zbus::zvariant::Value::new(params));
+        }
+        if let Some(secure_boot) = config.secure_boot {
+            settings.insert("SecureBoot", zbus::zvariant::Value::new(secure_boot));
+        }
+        Ok(self.bootloader_proxy.set_config(settings).await?)
+    }
+}
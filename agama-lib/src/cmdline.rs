@@ -0,0 +1,206 @@
+//! Kernel command line parameters for the autoinstallation
+//!
+//! The following parameters are recognized:
+//!
+//! * `agama.auto=<url>`: location of the profile (e.g.,
+//!   `agama.auto=http://192.168.122.1/profile.json`).
+//! * `agama.install=1`: starts the installation once the profile is loaded.
+//! * `agama.<key>=<value>`: overrides a setting (e.g., `agama.software.product=Tumbleweed`).
+//!   Collections are written in JSON and values with spaces can be quoted
+//!   (e.g., `agama.user.fullName="Jane Doe"`).
+use crate::install_settings::InstallSettings;
+use crate::layers::set_text_value;
+use crate::settings::SettingsError;
+use std::path::Path;
+use std::{fs, io, mem};
+
+/// Default location of the kernel command line
+pub const CMDLINE_PATH: &str = "/proc/cmdline";
+
+const PREFIX: &str = "agama.";
+
+/// Autoinstallation parameters from the kernel command line
+///
+/// ```
+/// # use agama_lib::cmdline::KernelCmdline;
+/// let cmdline = KernelCmdline::parse("quiet agama.auto=http://example.net/profile.json agama.install=1");
+/// assert_eq!(cmdline.profile_url.unwrap(), "http://example.net/profile.json");
+/// assert!(cmdline.install);
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct KernelCmdline {
+    /// Location of the profile (`agama.auto`)
+    pub profile_url: Option<String>,
+    /// Whether to start the installation (`agama.install`)
+    pub install: bool,
+    /// Settings to override, as pairs of key and value
+    pub overrides: Vec<(String, String)>,
+}
+
+impl KernelCmdline {
+    /// Reads the parameters from the given file (usually [CMDLINE_PATH])
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Parses the parameters, ignoring the ones which do not start with `agama.`
+    pub fn parse(cmdline: &str) -> Self {
+        let mut result = Self::default();
+        for param in split_params(cmdline) {
+            let Some((key, value)) = param.strip_prefix(PREFIX).and_then(|p| p.split_once('='))
+            else {
+                continue;
+            };
+
+            match key {
+                "auto" => result.profile_url = Some(value.to_string()),
+                "install" => {
+                    result.install = matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")
+                }
+                _ => result.overrides.push((key.to_string(), value.to_string())),
+            }
+        }
+        result
+    }
+
+    /// Whether there is anything to do
+    pub fn is_empty(&self) -> bool {
+        self.profile_url.is_none() && !self.install && self.overrides.is_empty()
+    }
+
+    /// Builds the settings defined by the overrides
+    ///
+    /// It fails if any key does not correspond to a known setting.
+    pub fn settings(&self) -> Result<InstallSettings, SettingsError> {
+        let mut settings = InstallSettings::default();
+        for (key, value) in &self.overrides {
+            let setting = InstallSettings::find_setting(key)
+                .ok_or_else(|| SettingsError::UnknownKey(key.clone()))?;
+            set_text_value(&mut settings, &setting, value.clone())?;
+        }
+        Ok(settings)
+    }
+}
+
+/// Splits the command line into parameters, taking the double quotes into account
+///
+/// As the kernel does, the whitespace between double quotes does not split a parameter and the
+/// quotes are removed only when they wrap the whole parameter or its value, so the ones in a JSON
+/// value (e.g., `agama.storage.devices=[{"name":"/dev/sda"}]`) are kept.
+fn split_params(cmdline: &str) -> Vec<String> {
+    let mut params = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in cmdline.chars() {
+        match c {
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    params.push(unquote(&mem::take(&mut current)));
+                }
+            }
+            c => {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                current.push(c)
+            }
+        }
+    }
+    if !current.is_empty() {
+        params.push(unquote(&current));
+    }
+    params
+}
+
+/// Removes the double quotes around a parameter (`"key=value"`) or its value (`key="value"`)
+fn unquote(param: &str) -> String {
+    let strip = |s: &str| {
+        s.strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .map(str::to_string)
+    };
+    if let Some(unquoted) = strip(param) {
+        return unquoted;
+    }
+    match param.split_once('=') {
+        Some((key, value)) => match strip(value) {
+            Some(value) => format!("{key}={value}"),
+            None => param.to_string(),
+        },
+        None => param.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse() {
+        let cmdline = KernelCmdline::parse(
+            "BOOT_IMAGE=/boot/vmlinuz agama.software.product=Tumbleweed \
+             agama.user.fullName=\"Jane Doe\" agama.install=0 splash=silent\n",
+        );
+        assert_eq!(cmdline.profile_url, None);
+        assert!(!cmdline.install);
+        assert_eq!(
+            cmdline.overrides,
+            vec![
+                ("software.product".to_string(), "Tumbleweed".to_string()),
+                ("user.fullName".to_string(), "Jane Doe".to_string()),
+            ]
+        );
+
+        let settings = cmdline.settings().unwrap();
+        assert_eq!(settings.software.unwrap().product.unwrap(), "Tumbleweed");
+        let user = settings.user.unwrap().first_user.unwrap();
+        assert_eq!(user.full_name.unwrap(), "Jane Doe");
+
+        assert!(KernelCmdline::parse("quiet splash").is_empty());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let cmdline = KernelCmdline::parse(
+            "agama.storage.devices=[{\"name\":\"/dev/sda\"}] \
+             \"agama.user.fullName=Jane Doe\"",
+        );
+        assert_eq!(
+            cmdline.overrides,
+            vec![
+                (
+                    "storage.devices".to_string(),
+                    "[{\"name\":\"/dev/sda\"}]".to_string()
+                ),
+                ("user.fullName".to_string(), "Jane Doe".to_string()),
+            ]
+        );
+
+        let settings = cmdline.settings().unwrap();
+        let devices = settings.storage.unwrap().devices;
+        assert_eq!(devices[0].name.as_deref(), Some("/dev/sda"));
+    }
+
+    #[test]
+    fn test_unknown_setting() {
+        let cmdline = KernelCmdline::parse("agama.software.produt=ALP");
+        assert_eq!(
+            cmdline.settings().unwrap_err(),
+            SettingsError::UnknownKey("software.produt".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "quiet agama.auto=file:///profile.jsonnet agama.install=1"
+        )
+        .unwrap();
+        let cmdline = KernelCmdline::read(file.path()).unwrap();
+        assert_eq!(cmdline.profile_url.unwrap(), "file:///profile.jsonnet");
+        assert!(cmdline.install);
+    }
+}
@@ -0,0 +1,213 @@
+//! Structural comparison of installation settings
+//!
+//! The settings are compared key by key, using the same dotted notation than the command line
+//! (e.g., `"user.userName"`). Lists (e.g., `"software.patterns"`) are compared as a whole.
+use crate::install_settings::InstallSettings;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Value which replaces the secrets (e.g., passwords) in the changes
+pub const MASKED_SECRET: &str = "********";
+
+/// Kind of change
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// Change of a single setting
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingChange {
+    /// Setting key (e.g., "software.product")
+    pub key: String,
+    pub kind: ChangeKind,
+    /// Original value, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    /// New value, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Returns the changes needed to go from one set of settings to another
+///
+/// Secrets are compared, but their values are masked (see [MASKED_SECRET]) in the result.
+///
+/// ```
+/// # use agama_lib::diff::{diff, ChangeKind};
+/// # use agama_lib::install_settings::{InstallSettings, SoftwareSettings};
+/// let from = InstallSettings::default();
+/// let to = InstallSettings {
+///   software: Some(SoftwareSettings { product: Some("ALP".to_string()), ..Default::default() }),
+///   ..Default::default()
+/// };
+/// let changes = diff(&from, &to);
+/// assert_eq!(changes[0].key, "software.product");
+/// assert_eq!(changes[0].kind, ChangeKind::Added);
+/// ```
+pub fn diff(from: &InstallSettings, to: &InstallSettings) -> Vec<SettingChange> {
+    let (from, masked_from) = (flatten(from), flatten(&masked(from)));
+    let (to, masked_to) = (flatten(to), flatten(&masked(to)));
+
+    let mut changes = vec![];
+    for (key, old) in &from {
+        match to.get(key) {
+            None => changes.push(SettingChange {
+                key: key.clone(),
+                kind: ChangeKind::Removed,
+                old: masked_from.get(key).cloned(),
+                new: None,
+            }),
+            Some(new) if new != old => changes.push(SettingChange {
+                key: key.clone(),
+                kind: ChangeKind::Changed,
+                old: masked_from.get(key).cloned(),
+                new: masked_to.get(key).cloned(),
+            }),
+            _ => {}
+        }
+    }
+
+    for key in to.keys().filter(|k| !from.contains_key(*k)) {
+        changes.push(SettingChange {
+            key: key.clone(),
+            kind: ChangeKind::Added,
+            old: None,
+            new: masked_to.get(key).cloned(),
+        });
+    }
+
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+/// Returns a copy of the settings which replaces the secrets with [MASKED_SECRET]
+///
+/// Use it before showing the settings to the user (e.g., in `config show`).
+pub fn masked(settings: &InstallSettings) -> InstallSettings {
+    let mut settings = settings.clone();
+    for (_, secret) in settings.secrets_mut() {
+        if secret.is_some() {
+            *secret = Some(MASKED_SECRET.to_string());
+        }
+    }
+    settings
+}
+
+/// Converts the settings into a map which uses dotted keys
+pub(crate) fn flatten(settings: &InstallSettings) -> BTreeMap<String, Value> {
+    let mut result = BTreeMap::new();
+    flatten_value("", settings.to_profile(), &mut result);
+    result
+}
+
+fn flatten_value(prefix: &str, value: Value, result: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_value(&key, value, result);
+            }
+        }
+        Value::Null => {}
+        _ => {
+            result.insert(prefix.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install_settings::{
+        FirstUserSettings, SoftwareSettings, StorageSettings, UserSettings,
+    };
+
+    fn user_settings(user_name: &str, password: &str) -> Option<UserSettings> {
+        Some(UserSettings {
+            first_user: Some(FirstUserSettings {
+                user_name: Some(user_name.to_string()),
+                password: Some(password.to_string()),
+                ..Default::default()
+            }),
+            root: None,
+        })
+    }
+
+    #[test]
+    fn test_masked() {
+        let settings = InstallSettings {
+            storage: Some(StorageSettings {
+                encryption_password: Some("luks!".to_string()),
+                lvm: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(masked(&settings)).unwrap(),
+            serde_json::json!({"storage": {"encryptionPassword": MASKED_SECRET, "lvm": true, "devices": []}})
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let from = InstallSettings {
+            user: user_settings("jane", "12345"),
+            software: Some(SoftwareSettings {
+                product: Some("ALP".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let to = InstallSettings {
+            user: user_settings("john", "54321"),
+            software: Some(SoftwareSettings {
+                patterns: Some(vec!["base".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let changes = diff(&from, &to);
+        assert_eq!(
+            changes,
+            vec![
+                SettingChange {
+                    key: "software.patterns".to_string(),
+                    kind: ChangeKind::Added,
+                    old: None,
+                    new: Some(serde_json::json!(["base"]))
+                },
+                SettingChange {
+                    key: "software.product".to_string(),
+                    kind: ChangeKind::Removed,
+                    old: Some("ALP".into()),
+                    new: None
+                },
+                SettingChange {
+                    key: "user.password".to_string(),
+                    kind: ChangeKind::Changed,
+                    old: Some(MASKED_SECRET.into()),
+                    new: Some(MASKED_SECRET.into())
+                },
+                SettingChange {
+                    key: "user.userName".to_string(),
+                    kind: ChangeKind::Changed,
+                    old: Some("jane".into()),
+                    new: Some("john".into())
+                },
+            ]
+        );
+        assert!(diff(&from, &from).is_empty());
+    }
+}
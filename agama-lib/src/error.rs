@@ -12,6 +12,8 @@ pub enum ServiceError {
     // specific error will be printed too
     #[error("Error: {0}")]
     Anyhow(#[from] anyhow::Error),
+    #[error("Could not {0}")]
+    Failed(String),
 }
 
 #[derive(Error, Debug)]
@@ -26,6 +28,14 @@ pub enum ProfileError {
     InputOutputError(#[from] io::Error),
     #[error("The profile is not a valid JSON file")]
     FormatError(#[from] serde_json::Error),
+    #[error("The profile is not a valid YAML file: '{0}'")]
+    YamlFormatError(#[from] serde_yaml::Error),
+    #[error("The profile must be evaluated before using it")]
+    NotEvaluated,
+    #[error("The AutoYaST profile is not a valid XML file: '{0}'")]
+    XmlFormatError(#[from] roxmltree::Error),
+    #[error("The exported profile is not valid: '{0:?}'")]
+    InvalidExport(Vec<String>),
 }
 
 #[derive(Error, Debug)]
@@ -33,5 +43,9 @@ pub enum WrongParameter {
     #[error("Unknown product '{0}'. Available products: '{1:?}'")]
     UnknownProduct(String, Vec<String>),
     #[error("Wrong user parameters: '{0:?}'")]
-    WrongUser(Vec<String>)
+    WrongUser(Vec<String>),
+    #[error("Unknown language '{0}'. Available languages: '{1:?}'")]
+    UnknownLanguage(String, Vec<String>),
+    #[error("Unknown keyboard '{0}'. Available keyboards: '{1:?}'")]
+    UnknownKeymap(String, Vec<String>),
 }
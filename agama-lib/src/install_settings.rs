@@ -1,18 +1,29 @@
 //! Configuration settings handling
 //!
 //! This module implements the mechanisms to load and store the installation settings.
-use crate::settings::{SettingObject, SettingValue, Settings};
+use crate::bootloader::BootloaderType;
+use crate::settings::{
+    suggest, MergeStrategy, SettingArgError, SettingDescription, SettingObject, SettingValue,
+    Settings, SettingsError, ValueSource,
+};
+use crate::storage::{
+    DeviceSelector, EncryptionMethod, IscsiAuth, IscsiStartup, LogicalVolume, PartitionAction,
+    PbkdFunction, Raid, SelectorError, SpacePolicy, StorageDevice, VolumeGroup,
+};
 use agama_derive::Settings;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use std::default::Default;
+use std::io;
 use std::str::FromStr;
 
 /// Settings scopes
 ///
 /// They are used to limit the reading/writing of settings. For instance, if the Scope::Users is
 /// given, only the data related to users (UsersStore) are read/written.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Scope {
     /// User settings
     Users,
@@ -20,14 +31,36 @@ pub enum Scope {
     Software,
     /// Storage settings
     Storage,
+    /// Localization settings
+    Localization,
+    /// Bootloader settings
+    Bootloader,
+    /// iSCSI settings
+    Iscsi,
 }
 
 impl Scope {
     /// Returns known scopes
     ///
     // TODO: we can rely on strum so we do not forget to add them
-    pub fn all() -> [Scope; 3] {
-        [Scope::Software, Scope::Storage, Scope::Users]
+    pub fn all() -> [Scope; 6] {
+        [
+            Scope::Bootloader,
+            Scope::Iscsi,
+            Scope::Localization,
+            Scope::Software,
+            Scope::Storage,
+            Scope::Users,
+        ]
+    }
+
+    /// Returns the scope of a setting key (e.g., `Scope::Users` for `"root.password"`)
+    pub fn from_key(key: &str) -> Option<Scope> {
+        let (name, _) = key.split_once('.')?;
+        match name {
+            "user" | "root" => Some(Scope::Users),
+            _ => name.parse().ok(),
+        }
     }
 }
 
@@ -39,6 +72,9 @@ impl FromStr for Scope {
             "users" => Ok(Self::Users),
             "software" => Ok(Self::Software),
             "storage" => Ok(Self::Storage),
+            "localization" => Ok(Self::Localization),
+            "bootloader" => Ok(Self::Bootloader),
+            "iscsi" => Ok(Self::Iscsi),
             _ => Err("Unknown section"),
         }
     }
@@ -48,15 +84,40 @@ impl FromStr for Scope {
 ///
 /// This struct represents installation settings. It serves as an entry point and it is composed of
 /// other structs which hold the settings for each area ("users", "software", etc.).
-#[derive(Debug, Default, Serialize, Deserialize)]
+///
+/// The profiles JSON Schema is generated from this struct (see
+/// [profile_schema](crate::profile::profile_schema)), so the doc comments of the fields are used
+/// as descriptions.
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(
+    title = "Profile",
+    description = "Profile definition for automated installation"
+)]
 pub struct InstallSettings {
     #[serde(default, flatten)]
+    #[settings(flatten, scope = "users")]
     pub user: Option<UserSettings>,
-    #[serde(default)]
+    /// Software settings (e.g., product to install)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested, scope = "software")]
     pub software: Option<SoftwareSettings>,
-    #[serde(default)]
+    /// iSCSI settings (the targets are logged in before calculating the storage proposal)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested, scope = "iscsi")]
+    pub iscsi: Option<IscsiSettings>,
+    /// Storage settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested, scope = "storage")]
     pub storage: Option<StorageSettings>,
+    /// Localization settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested, scope = "localization")]
+    pub localization: Option<LocalizationSettings>,
+    /// Bootloader settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested, scope = "bootloader")]
+    pub bootloader: Option<BootloaderSettings>,
 }
 
 impl InstallSettings {
@@ -66,6 +127,10 @@ impl InstallSettings {
             scopes.push(Scope::Users);
         }
 
+        if self.iscsi.is_some() {
+            scopes.push(Scope::Iscsi);
+        }
+
         if self.storage.is_some() {
             scopes.push(Scope::Storage);
         }
@@ -73,183 +138,486 @@ impl InstallSettings {
         if self.software.is_some() {
             scopes.push(Scope::Software);
         }
+
+        if self.localization.is_some() {
+            scopes.push(Scope::Localization);
+        }
+
+        if self.bootloader.is_some() {
+            scopes.push(Scope::Bootloader);
+        }
         scopes
     }
-}
 
-impl Settings for InstallSettings {
-    fn add(&mut self, attr: &str, value: SettingObject) -> Result<(), &'static str> {
-        if let Some((ns, id)) = attr.split_once('.') {
-            match ns {
-                "software" => {
-                    let software = self.software.get_or_insert(Default::default());
-                    software.add(id, value)?
-                }
-                "user" => {
-                    let user = self.user.get_or_insert(Default::default());
-                    user.add(id, value)?
-                }
-                "storage" => {
-                    let storage = self.storage.get_or_insert(Default::default());
-                    storage.add(id, value)?
-                }
-                _ => return Err("unknown attribute"),
-            }
+    /// Finds a setting given its key, in camelCase or snake_case (e.g., `"user.userName"`)
+    pub fn find_setting(key: &str) -> Option<SettingDescription> {
+        Self::settings().into_iter().find(|s| s.matches(key))
+    }
+
+    /// Returns the known key which is the most similar to the given one
+    pub fn suggest_setting(key: &str) -> Option<String> {
+        let settings = Self::settings();
+        suggest(key, settings.iter().map(|s| s.key.as_str())).map(str::to_string)
+    }
+
+    /// Serializes the settings as a profile, including the root password
+    ///
+    /// The root password is write-only, so it is not shown by accident (e.g., by `config show`).
+    /// Use this function where the profile must be complete (e.g., when exporting it), after
+    /// handling the secrets (see [InstallSettings::secrets_mut]).
+    pub fn to_profile(&self) -> serde_json::Value {
+        let mut profile = serde_json::to_value(self).expect("serializable settings");
+        let root_password = self
+            .user
+            .as_ref()
+            .and_then(|u| u.root.as_ref())
+            .and_then(|r| r.password.clone());
+        if let Some(password) = root_password {
+            profile["root"]["password"] = serde_json::Value::String(password);
         }
-        Ok(())
+        profile
     }
 
-    fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), &'static str> {
-        if let Some((ns, id)) = attr.split_once('.') {
-            match ns {
-                "software" => {
-                    let software = self.software.get_or_insert(Default::default());
-                    software.set(id, value)?
-                }
-                "user" => {
-                    let user = self.user.get_or_insert(Default::default());
-                    // User settings are flatten. Pass the full attribute name.
-                    user.set(attr, value)?
-                }
-                "root" => {
-                    let root = self.user.get_or_insert(Default::default());
-                    // Root settings are flatten. Pass the full attribute name.
-                    root.set(attr, value)?
-                }
-                "storage" => {
-                    let storage = self.storage.get_or_insert(Default::default());
-                    storage.set(id, value)?
-                }
-                _ => return Err("unknown attribute"),
+    /// Returns the secret values (e.g., passwords) and their keys, so they can be masked, removed
+    /// or resolved
+    ///
+    /// Besides the settings marked as secrets, it includes the passwords of the iSCSI targets,
+    /// whose keys contain the name of the target (e.g.,
+    /// `iscsi.targets[iqn.2023-01.com.example:storage].password`).
+    pub fn secrets_mut(&mut self) -> Vec<(String, &mut Option<String>)> {
+        let mut secrets = vec![];
+        if let Some(user) = &mut self.user {
+            if let Some(first_user) = &mut user.first_user {
+                secrets.push(("user.password".to_string(), &mut first_user.password));
+            }
+            if let Some(root) = &mut user.root {
+                secrets.push(("root.password".to_string(), &mut root.password));
             }
         }
-        Ok(())
-    }
 
-    fn merge(&mut self, other: &Self) {
-        if let Some(other_software) = &other.software {
-            let software = self.software.get_or_insert(Default::default());
-            software.merge(other_software);
+        if let Some(storage) = &mut self.storage {
+            secrets.push((
+                "storage.encryptionPassword".to_string(),
+                &mut storage.encryption_password,
+            ));
         }
 
-        if let Some(other_user) = &other.user {
-            let user = self.user.get_or_insert(Default::default());
-            user.merge(other_user);
+        if let Some(iscsi) = &mut self.iscsi {
+            for target in &mut iscsi.targets {
+                let key = format!("iscsi.targets[{}]", target.name);
+                secrets.push((format!("{key}.password"), &mut target.password));
+                secrets.push((
+                    format!("{key}.reversePassword"),
+                    &mut target.reverse_password,
+                ));
+            }
         }
+        secrets
+    }
 
-        if let Some(other_storage) = &other.storage {
-            let storage = self.storage.get_or_insert(Default::default());
-            storage.merge(other_storage);
+    /// Replaces the references in the secrets (e.g., `"@env:ROOT_PASSWORD"`) with their values
+    ///
+    /// See [ValueSource::secret_reference] for the supported references. The `prompt` function
+    /// is called with the key of the secret (see [InstallSettings::secrets_mut]) for `"@prompt"`.
+    pub fn resolve_secrets(
+        &mut self,
+        mut prompt: impl FnMut(&str) -> io::Result<String>,
+    ) -> Result<(), SettingArgError> {
+        for (key, secret) in self.secrets_mut() {
+            let Some(source) = secret.as_deref().and_then(ValueSource::secret_reference) else {
+                continue;
+            };
+            *secret = Some(source.read(&key, io::empty(), &mut prompt)?);
         }
+        Ok(())
     }
 }
 
 /// User settings
 ///
 /// Holds the user settings for the installation.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UserSettings {
-    #[serde(rename = "user")]
+    /// First user settings
+    #[serde(rename = "user", skip_serializing_if = "Option::is_none")]
+    #[settings(nested)]
     pub first_user: Option<FirstUserSettings>,
+    /// Root authentication settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested)]
     pub root: Option<RootUserSettings>,
 }
 
-impl Settings for UserSettings {
-    fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), &'static str> {
-        if let Some((ns, id)) = attr.split_once('.') {
-            match ns {
-                "user" => {
-                    let first_user = self.first_user.get_or_insert(Default::default());
-                    first_user.set(id, value)?
-                }
-                "root" => {
-                    let root_user = self.root.get_or_insert(Default::default());
-                    root_user.set(id, value)?
-                }
-                _ => return Err("unknown attribute"),
-            }
-        }
-        Ok(())
-    }
-
-    fn merge(&mut self, other: &Self) {
-        if let Some(other_first_user) = &other.first_user {
-            let first_user = self.first_user.get_or_insert(Default::default());
-            first_user.merge(other_first_user);
-        }
-    }
-}
-
 /// First user settings
 ///
 /// Holds the settings for the first user.
-#[derive(Debug, Default, Settings, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FirstUserSettings {
     /// First user's full name
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub full_name: Option<String>,
     /// First user's username
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub user_name: Option<String>,
     /// First user's password (in clear text)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(secret)]
     pub password: Option<String>,
     /// Whether auto-login should enabled or not
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub autologin: Option<bool>,
 }
 
 /// Root user settings
 ///
 /// Holds the settings for the root user.
-#[derive(Debug, Default, Settings, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RootUserSettings {
     /// Root's password (in clear text)
     #[serde(skip_serializing)]
+    #[settings(secret)]
     pub password: Option<String>,
     /// Root SSH public key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(alias = "ssh_key")]
     pub ssh_public_key: Option<String>,
 }
 
 /// Storage settings for installation
-#[derive(Debug, Default, Settings, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StorageSettings {
     /// Whether LVM should be enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lvm: Option<bool>,
     /// Encryption password for the storage devices (in clear text)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(secret)]
     pub encryption_password: Option<String>,
-    /// Devices to use in the installation
+    /// Encryption settings (method, key derivation function, etc.)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested)]
+    pub encryption: Option<EncryptionSettings>,
+    /// Btrfs settings of the root file system (snapshots and subvolumes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested)]
+    pub btrfs: Option<BtrfsSettings>,
+    /// Expert layout: software RAIDs, LVM volume groups and logical volumes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[settings(nested)]
+    pub expert: Option<ExpertStorageSettings>,
+    /// Device to install the bootloader to (e.g., "/dev/sda"); by default, it is chosen by the
+    /// storage proposal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boot_device: Option<String>,
+    /// Storage devices to install the system to, given by name or by selector (e.g.,
+    /// `{"biggest": true}`)
+    #[serde(default)]
     #[collection_setting]
+    #[settings(merge_key = "key()")]
     pub devices: Vec<Device>,
+    /// How to make space for the new system (delete, resize, keep or custom)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub space_policy: Option<SpacePolicy>,
+    /// Actions to perform on the existing partitions when using the custom space policy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[collection_setting]
+    #[settings(merge_key = "device")]
+    pub space_actions: Vec<SpaceAction>,
+}
+
+/// Encryption settings for the storage devices
+///
+/// The encryption is enabled by setting the encryption password.
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionSettings {
+    /// Encryption method (luks1, luks2 or tpm_fde to unlock the devices using the TPM2 chip)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<EncryptionMethod>,
+    /// Password-based key derivation function for LUKS2 (pbkdf2, argon2i or argon2id)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pbkdf: Option<PbkdFunction>,
+}
+
+/// Expert storage layout
+///
+/// It is checked against the available devices before sending it to the storage service (see
+/// [validate_layout](crate::storage::validate_layout)).
+#[derive(Clone, Debug, Default, PartialEq, Settings, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpertStorageSettings {
+    /// Software RAIDs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[collection_setting]
+    #[settings(merge_key = "name")]
+    pub raids: Vec<Raid>,
+    /// LVM volume groups
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[collection_setting]
+    #[settings(merge_key = "name")]
+    pub volume_groups: Vec<VolumeGroup>,
+    /// LVM logical volumes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[collection_setting]
+    #[settings(merge_key = "name")]
+    pub logical_volumes: Vec<LogicalVolume>,
+}
+
+impl ExpertStorageSettings {
+    pub fn is_empty(&self) -> bool {
+        self.raids.is_empty() && self.volume_groups.is_empty() && self.logical_volumes.is_empty()
+    }
+}
+
+/// Btrfs settings of the root file system
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BtrfsSettings {
+    /// Whether to enable the snapshots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshots: Option<bool>,
+    /// Subvolumes to create in the root file system
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[collection_setting]
+    #[settings(merge_key = "path")]
+    pub subvolumes: Vec<Subvolume>,
+}
+
+/// Btrfs subvolume
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct Subvolume {
+    /// Path of the subvolume, relative to the root file system (e.g., "var")
+    pub path: String,
+    /// Whether copy-on-write is enabled (true by default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_on_write: Option<bool>,
+}
+
+impl TryFrom<SettingObject> for Subvolume {
+    type Error = SettingsError;
+
+    fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
+        Ok(Subvolume {
+            path: value.required("path")?.try_into()?,
+            copy_on_write: value.get("copyOnWrite").map(bool::try_from).transpose()?,
+        })
+    }
 }
 
-/// Device to use in the installation
-#[derive(Debug, Serialize, Deserialize)]
+/// Device to use in the installation, given by its name or by a selector
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct Device {
-    /// Device name (e.g., "/dev/sda")
-    pub name: String,
+    /// Storage device name (e.g., "/dev/sda")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub selector: DeviceSelector,
+}
+
+impl Device {
+    /// Builds a device given its name
+    pub fn with_name(name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Identifies the device when merging: the name or, if there is none, the selector
+    pub fn key(&self) -> (Option<&str>, Option<&DeviceSelector>) {
+        match &self.name {
+            Some(name) => (Some(name), None),
+            None => (None, Some(&self.selector)),
+        }
+    }
+
+    /// Returns the name of the device, selecting it from `available` if it is not given
+    ///
+    /// It fails if neither the name nor the selector is given.
+    pub fn resolve(&self, available: &[StorageDevice]) -> Result<String, SelectorError> {
+        match &self.name {
+            Some(name) => Ok(name.clone()),
+            None if self.selector.is_empty() => Err(SelectorError::Empty),
+            None => Ok(self.selector.select(available)?.name.clone()),
+        }
+    }
 }
 
 impl TryFrom<SettingObject> for Device {
-    type Error = &'static str;
+    type Error = SettingsError;
 
     fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
-        match value.0.get("name") {
-            Some(name) => Ok(Device {
-                name: name.clone().try_into()?,
-            }),
-            None => Err("'name' key not found"),
+        let device = Device {
+            name: value.get("name").map(String::try_from).transpose()?,
+            selector: DeviceSelector {
+                biggest: value.get("biggest").map(bool::try_from).transpose()?,
+                size: value.get("size").map(TryFrom::try_from).transpose()?,
+                model: value.get("model").map(String::try_from).transpose()?,
+                serial: value.get("serial").map(String::try_from).transpose()?,
+                transport: value.get("transport").map(String::try_from).transpose()?,
+            },
+        };
+        if device.name.is_none() && device.selector.is_empty() {
+            return Err(SettingsError::missing_field("name"));
         }
+        Ok(device)
+    }
+}
+
+/// Action to perform on an existing partition
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct SpaceAction {
+    /// Partition name (e.g., "/dev/sda1")
+    pub device: String,
+    /// Action to perform (delete, resize or keep)
+    pub action: PartitionAction,
+}
+
+impl TryFrom<SettingObject> for SpaceAction {
+    type Error = SettingsError;
+
+    fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
+        Ok(SpaceAction {
+            device: value.required("device")?.try_into()?,
+            action: value.required("action")?.try_into()?,
+        })
     }
 }
 
 /// Software settings for installation
-#[derive(Debug, Default, Settings, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SoftwareSettings {
     /// ID of the product to install (e.g., "ALP", "Tumbleweed", etc.)
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub product: Option<String>,
+    /// Patterns to install (e.g., "base", "x11")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patterns: Option<Vec<String>>,
+    /// Packages to install (e.g., "vim", "git")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packages: Option<Vec<String>>,
+}
+
+/// Bootloader settings for installation
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BootloaderSettings {
+    /// Bootloader to install (grub2 or systemd-boot)
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub bootloader_type: Option<BootloaderType>,
+    /// Time to wait (in seconds) before booting the default entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+    /// Extra parameters for the kernel command line (e.g., "console=ttyS0 nomodeset")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_kernel_params: Option<String>,
+    /// Whether to enable the secure boot support
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secure_boot: Option<bool>,
+}
+
+/// iSCSI settings for installation
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IscsiSettings {
+    /// iSCSI qualified name of the initiator (e.g., "iqn.2023-01.com.example:node1")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initiator: Option<String>,
+    /// Targets to discover and log in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[collection_setting]
+    #[settings(merge_key = "name", secret_fields = "password,reverse_password")]
+    pub targets: Vec<IscsiTarget>,
+}
+
+/// iSCSI target
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct IscsiTarget {
+    /// Target name (e.g., "iqn.2023-01.com.example:storage")
+    pub name: String,
+    /// Address of the portal offering the target
+    pub address: String,
+    /// Port of the portal (3260 by default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u32>,
+    /// When to log in the target (onboot, manual or automatic); onboot by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup: Option<IscsiStartup>,
+    /// CHAP user name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// CHAP password
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// User name to authenticate the target (mutual CHAP)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverse_username: Option<String>,
+    /// Password to authenticate the target (mutual CHAP)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverse_password: Option<String>,
+}
+
+impl IscsiTarget {
+    /// Returns the credentials to discover and log in the target
+    pub fn auth(&self) -> IscsiAuth {
+        IscsiAuth {
+            username: self.username.clone(),
+            password: self.password.clone(),
+            reverse_username: self.reverse_username.clone(),
+            reverse_password: self.reverse_password.clone(),
+        }
+    }
+}
+
+impl TryFrom<SettingObject> for IscsiTarget {
+    type Error = SettingsError;
+
+    fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
+        Ok(IscsiTarget {
+            name: value.required("name")?.try_into()?,
+            address: value.required("address")?.try_into()?,
+            port: value.get("port").map(u32::try_from).transpose()?,
+            startup: value
+                .get("startup")
+                .map(IscsiStartup::try_from)
+                .transpose()?,
+            username: value.get("username").map(String::try_from).transpose()?,
+            password: value.get("password").map(String::try_from).transpose()?,
+            reverse_username: value
+                .get("reverseUsername")
+                .map(String::try_from)
+                .transpose()?,
+            reverse_password: value
+                .get("reversePassword")
+                .map(String::try_from)
+                .transpose()?,
+        })
+    }
+}
+
+/// Localization settings for installation
+#[derive(Clone, Debug, Default, Settings, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizationSettings {
+    /// System language ID (e.g., "en_US")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Keyboard layout ID (e.g., "us" or "de-nodeadkeys")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyboard: Option<String>,
 }
 
 #[cfg(test)]
@@ -267,4 +635,488 @@ mod tests {
         user1.merge(&user2);
         assert_eq!(user1.full_name.unwrap(), "Jane Doe")
     }
+
+    #[test]
+    fn test_merge_sections() {
+        let mut settings = InstallSettings::default();
+        let mut other = InstallSettings::default();
+        other
+            .set("root.ssh_key", SettingValue("ssh-rsa AAAA".to_string()))
+            .unwrap();
+        other
+            .set("software.product", SettingValue("ALP".to_string()))
+            .unwrap();
+        settings.merge(&other);
+
+        let root = settings.user.unwrap().root.unwrap();
+        assert_eq!(root.ssh_public_key.unwrap(), "ssh-rsa AAAA");
+        assert_eq!(settings.software.unwrap().product.unwrap(), "ALP");
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let storage = |names: &[&str]| InstallSettings {
+            storage: Some(StorageSettings {
+                devices: names.iter().map(|n| Device::with_name(n)).collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let names = |settings: InstallSettings| -> Vec<String> {
+            settings
+                .storage
+                .unwrap()
+                .devices
+                .into_iter()
+                .filter_map(|d| d.name)
+                .collect()
+        };
+        let current = storage(&["/dev/sda", "/dev/sdb"]);
+        let other = storage(&["/dev/sdb", "/dev/sdc"]);
+
+        let mut settings = current.clone();
+        settings.merge(&other);
+        assert_eq!(names(settings), ["/dev/sdb", "/dev/sdc"]);
+
+        let mut settings = current.clone();
+        settings.merge_with(&other, Some(MergeStrategy::Append));
+        assert_eq!(
+            names(settings),
+            ["/dev/sda", "/dev/sdb", "/dev/sdb", "/dev/sdc"]
+        );
+
+        let mut settings = current.clone();
+        settings.merge_with(&other, Some(MergeStrategy::MergeByKey));
+        assert_eq!(names(settings), ["/dev/sda", "/dev/sdb", "/dev/sdc"]);
+
+        // the devices are identified by the name or, if there is none, by the selector
+        let nvme = Device {
+            selector: DeviceSelector {
+                transport: Some("nvme".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut settings = current.clone();
+        let mut other = storage(&["/dev/sdb"]);
+        let devices = &mut other.storage.as_mut().unwrap().devices;
+        devices[0].selector.biggest = Some(true);
+        devices.push(nvme.clone());
+        settings.merge_with(&other, Some(MergeStrategy::MergeByKey));
+        settings.merge_with(&other, Some(MergeStrategy::MergeByKey));
+        let devices = settings.storage.unwrap().devices;
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[1].selector.biggest, Some(true));
+        assert_eq!(devices[2], nvme);
+
+        // an empty collection does not replace the current one
+        let mut settings = current.clone();
+        settings.merge(&storage(&[]));
+        assert_eq!(names(settings), ["/dev/sda", "/dev/sdb"]);
+    }
+
+    #[test]
+    fn test_encryption_settings() {
+        let mut settings = InstallSettings::default();
+        settings
+            .set("storage.encryption.method", SettingValue("LUKS2".into()))
+            .unwrap();
+        settings
+            .set("storage.encryption.pbkdf", SettingValue("argon2id".into()))
+            .unwrap();
+        let error = settings
+            .set("storage.encryption.method", SettingValue("luks3".into()))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Wrong value for 'storage.encryption.method': \
+             expected one of luks1, luks2, tpm_fde: 'luks3'"
+        );
+
+        let encryption = settings.storage.unwrap().encryption.unwrap();
+        assert_eq!(encryption.method, Some(EncryptionMethod::Luks2));
+        assert_eq!(encryption.pbkdf, Some(PbkdFunction::Argon2id));
+        assert_eq!(
+            serde_json::to_string(&encryption).unwrap(),
+            r#"{"method":"luks2","pbkdf":"argon2id"}"#
+        );
+    }
+
+    #[test]
+    fn test_bootloader_settings() {
+        let mut settings = InstallSettings::default();
+        settings
+            .set("bootloader.type", SettingValue("systemd-boot".into()))
+            .unwrap();
+        settings
+            .set("bootloader.timeout", SettingValue("5".into()))
+            .unwrap();
+        settings
+            .set(
+                "bootloader.extra_kernel_params",
+                SettingValue("console=ttyS0".into()),
+            )
+            .unwrap();
+        settings
+            .set("storage.boot_device", SettingValue("/dev/sda".into()))
+            .unwrap();
+        let error = settings
+            .set("bootloader.type", SettingValue("lilo".into()))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Wrong value for 'bootloader.type': expected one of grub2, systemd-boot: 'lilo'"
+        );
+
+        assert_eq!(
+            settings.defined_scopes(),
+            vec![Scope::Storage, Scope::Bootloader]
+        );
+        let bootloader = settings.bootloader.unwrap();
+        assert_eq!(
+            bootloader.bootloader_type,
+            Some(BootloaderType::SystemdBoot)
+        );
+        assert_eq!(
+            serde_json::to_string(&bootloader).unwrap(),
+            r#"{"type":"systemd-boot","timeout":5,"extraKernelParams":"console=ttyS0"}"#
+        );
+        assert_eq!(
+            Scope::from_key("bootloader.timeout"),
+            Some(Scope::Bootloader)
+        );
+    }
+
+    #[test]
+    fn test_space_policy() {
+        let action = |device: &str, action: &str| {
+            SettingObject::from([("device", device), ("action", action)])
+        };
+        let mut settings = InstallSettings::default();
+        settings
+            .set("storage.space_policy", SettingValue("custom".into()))
+            .unwrap();
+        settings
+            .add("storage.space_actions", action("/dev/sda1", "resize"))
+            .unwrap();
+        settings
+            .add("storage.space_actions", action("/dev/sda2", "delete"))
+            .unwrap();
+        let error = settings
+            .add("storage.space_actions", action("/dev/sda3", "format"))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Wrong value for 'storage.spaceActions': \
+             expected one of delete, resize, keep: 'format'"
+        );
+
+        let storage = settings.storage.unwrap();
+        assert_eq!(storage.space_policy, Some(SpacePolicy::Custom));
+        assert_eq!(
+            serde_json::to_value(&storage.space_actions).unwrap(),
+            serde_json::json!([
+                {"device": "/dev/sda1", "action": "resize"},
+                {"device": "/dev/sda2", "action": "delete"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_root_password_is_write_only() {
+        let mut settings = InstallSettings::default();
+        settings
+            .set("root.password", SettingValue("nots3cr3t".into()))
+            .unwrap();
+        settings
+            .set("root.ssh_public_key", SettingValue("ssh-rsa AAA".into()))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&settings).unwrap(),
+            serde_json::json!({"root": {"sshPublicKey": "ssh-rsa AAA"}})
+        );
+        assert_eq!(
+            settings.to_profile(),
+            serde_json::json!({"root": {"password": "nots3cr3t", "sshPublicKey": "ssh-rsa AAA"}})
+        );
+    }
+
+    #[test]
+    fn test_resolve_secrets() {
+        std::env::set_var("AGAMA_TEST_ROOT_PASSWORD", "s3cr3t");
+        let mut settings = InstallSettings::default();
+        settings
+            .set(
+                "root.password",
+                SettingValue("@env:AGAMA_TEST_ROOT_PASSWORD".into()),
+            )
+            .unwrap();
+        settings
+            .set("user.password", SettingValue("@prompt".into()))
+            .unwrap();
+        settings
+            .set("user.full_name", SettingValue("@prompt".into()))
+            .unwrap();
+        settings
+            .resolve_secrets(|key| Ok(format!("{key}!")))
+            .unwrap();
+
+        let user = settings.user.unwrap();
+        assert_eq!(user.root.unwrap().password.unwrap(), "s3cr3t");
+        let first_user = user.first_user.unwrap();
+        assert_eq!(first_user.password.unwrap(), "user.password!");
+        // only secrets are resolved
+        assert_eq!(first_user.full_name.unwrap(), "@prompt");
+
+        std::env::set_var("AGAMA_TEST_ISCSI_PASSWORD", "iscsi!");
+        let mut settings = InstallSettings::default();
+        settings
+            .add(
+                "iscsi.targets",
+                SettingObject::from([
+                    ("name", "iqn.2023-01.com.example:storage"),
+                    ("address", "192.168.1.10"),
+                    ("password", "@env:AGAMA_TEST_ISCSI_PASSWORD"),
+                    ("reversePassword", "@prompt"),
+                ]),
+            )
+            .unwrap();
+        settings
+            .resolve_secrets(|key| Ok(format!("{key}!")))
+            .unwrap();
+        let target = &settings.iscsi.unwrap().targets[0];
+        assert_eq!(target.password.as_deref(), Some("iscsi!"));
+        assert_eq!(
+            target.reverse_password.as_deref(),
+            Some("iscsi.targets[iqn.2023-01.com.example:storage].reversePassword!")
+        );
+    }
+
+    #[test]
+    fn test_secrets_mut() {
+        // all the settings marked as secrets are included
+        let mut settings = InstallSettings::default();
+        let secrets: Vec<_> = InstallSettings::settings()
+            .into_iter()
+            .filter(|s| s.secret)
+            .collect();
+        for setting in &secrets {
+            settings
+                .set(&setting.attr, SettingValue("s3cr3t".to_string()))
+                .unwrap();
+        }
+        let keys: Vec<_> = settings.secrets_mut().into_iter().map(|(k, _)| k).collect();
+        for setting in &secrets {
+            assert!(keys.contains(&setting.key), "{} is missing", setting.key);
+        }
+    }
+
+    #[test]
+    fn test_unset_and_remove() {
+        let device = |name: &str| SettingObject::from([("name", name)]);
+        let mut settings = InstallSettings::default();
+        settings
+            .set("root.password", SettingValue("nots3cr3t".to_string()))
+            .unwrap();
+        settings.unset("root.password").unwrap();
+        let root = settings.user.as_ref().unwrap().root.as_ref().unwrap();
+        assert_eq!(root.password, None);
+        assert!(settings.unset("storage.unknown").is_err());
+
+        settings.add("storage.devices", device("/dev/sda")).unwrap();
+        assert!(settings
+            .remove("storage.devices", device("/dev/sdb"))
+            .is_err());
+        settings
+            .remove("storage.devices", device("/dev/sda"))
+            .unwrap();
+        assert!(settings.storage.unwrap().devices.is_empty());
+    }
+
+    #[test]
+    fn test_btrfs_settings() {
+        let subvolume =
+            |path: &str, cow: &str| SettingObject::from([("path", path), ("copyOnWrite", cow)]);
+        let mut settings = InstallSettings::default();
+        settings
+            .set("storage.btrfs.snapshots", SettingValue("true".into()))
+            .unwrap();
+        settings
+            .add("storage.btrfs.subvolumes", subvolume("home", "true"))
+            .unwrap();
+        settings
+            .add("storage.btrfs.subvolumes", subvolume("var", "false"))
+            .unwrap();
+        settings
+            .remove("storage.btrfs.subvolumes", subvolume("home", "true"))
+            .unwrap();
+
+        let subvolumes = InstallSettings::find_setting("storage.btrfs.subvolumes").unwrap();
+        assert!(subvolumes.collection);
+        assert_eq!(subvolumes.scope, Some(Scope::Storage));
+        let btrfs = settings.storage.unwrap().btrfs.unwrap();
+        assert_eq!(
+            serde_json::to_value(&btrfs).unwrap(),
+            serde_json::json!({
+                "snapshots": true,
+                "subvolumes": [{"path": "var", "copyOnWrite": false}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_iscsi_settings() {
+        let mut settings = InstallSettings::default();
+        settings
+            .add(
+                "iscsi.targets",
+                SettingObject::from([
+                    ("name", "iqn.2023-01.com.example:storage"),
+                    ("address", "192.168.1.10"),
+                    ("startup", "automatic"),
+                    ("password", "s3cr3t"),
+                ]),
+            )
+            .unwrap();
+        let error = settings
+            .add(
+                "iscsi.targets",
+                SettingObject::from([("name", "iqn.2023-01.com.example:b")]),
+            )
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Missing 'address' for 'iscsi.targets'");
+
+        assert_eq!(Scope::from_key("iscsi.initiator"), Some(Scope::Iscsi));
+        assert_eq!(settings.defined_scopes(), vec![Scope::Iscsi]);
+        let iscsi = settings.iscsi.as_ref().unwrap();
+        assert_eq!(iscsi.targets[0].startup, Some(IscsiStartup::Automatic));
+        assert_eq!(iscsi.targets[0].auth().password.as_deref(), Some("s3cr3t"));
+
+        let secrets = settings.secrets_mut();
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(
+            secrets[0].0,
+            "iscsi.targets[iqn.2023-01.com.example:storage].password"
+        );
+        assert_eq!(secrets[0].1.as_deref(), Some("s3cr3t"));
+    }
+
+    #[test]
+    fn test_device_selector() {
+        let mut settings = InstallSettings::default();
+        settings
+            .add(
+                "storage.devices",
+                SettingObject::from([("name", "/dev/sda")]),
+            )
+            .unwrap();
+        settings
+            .add(
+                "storage.devices",
+                SettingObject::from([("transport", "nvme"), ("biggest", "true")]),
+            )
+            .unwrap();
+        let error = settings
+            .add("storage.devices", SettingObject::from([("size", "huge")]))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Wrong value for 'storage.devices': not a valid size constraint: 'huge'"
+        );
+
+        let available = vec![StorageDevice {
+            name: "/dev/nvme0n1".to_string(),
+            transport: Some("nvme".to_string()),
+            ..Default::default()
+        }];
+        let names: Vec<_> = settings
+            .storage
+            .unwrap()
+            .devices
+            .iter()
+            .map(|d| d.resolve(&available).unwrap())
+            .collect();
+        assert_eq!(names, ["/dev/sda", "/dev/nvme0n1"]);
+        assert_eq!(
+            Device::default().resolve(&available),
+            Err(SelectorError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_settings_errors() {
+        let mut settings = InstallSettings::default();
+        let error = settings
+            .set("user.autologin", SettingValue("maybe".to_string()))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Wrong value for 'user.autologin': not a valid boolean: 'maybe'"
+        );
+
+        let error = settings
+            .add("storage.devices", SettingObject::from([]))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Missing 'name' for 'storage.devices'");
+
+        let error = settings
+            .set("storage.devices", SettingValue("/dev/sda".to_string()))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            SettingsError::UnknownKey("storage.devices".to_string())
+        );
+    }
+
+    #[test]
+    fn test_settings() {
+        let settings = InstallSettings::settings();
+        let model = InstallSettings::default();
+        for setting in &settings {
+            assert!(
+                model.get(&setting.attr).is_ok(),
+                "{} not found",
+                setting.key
+            );
+            assert!(setting.scope.is_some());
+        }
+
+        let user_name = InstallSettings::find_setting("user.userName").unwrap();
+        assert_eq!(user_name.attr, "user.user_name");
+        assert_eq!(user_name.value_type, "string");
+        assert_eq!(user_name.description, "First user's username");
+        assert_eq!(user_name.scope, Some(Scope::Users));
+
+        let ssh_key = InstallSettings::find_setting("root.sshKey").unwrap();
+        assert_eq!(ssh_key.key, "root.sshPublicKey");
+
+        let devices = InstallSettings::find_setting("storage.devices").unwrap();
+        assert!(devices.collection);
+
+        assert_eq!(
+            InstallSettings::suggest_setting("user.username").unwrap(),
+            "user.userName"
+        );
+    }
+
+    #[test]
+    fn test_get() {
+        let mut settings = InstallSettings::default();
+        settings
+            .set("user.user_name", SettingValue("jane".to_string()))
+            .unwrap();
+        assert_eq!(settings.get("user.user_name").unwrap(), "jane");
+        assert_eq!(
+            settings.get("root.password").unwrap(),
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            settings.get("storage.devices").unwrap(),
+            serde_json::json!([])
+        );
+        assert_eq!(
+            settings.get("software.unknown"),
+            Err(SettingsError::UnknownKey("software.unknown".to_string()))
+        );
+    }
 }
@@ -0,0 +1,262 @@
+//! Layers of installation settings
+//!
+//! The settings can come from several layers: the installer itself, a profile, the kernel
+//! command line (see [crate::cmdline]) and the environment variables (e.g.,
+//! `AGAMA_SOFTWARE_PRODUCT=Tumbleweed`). Each layer overrides the previous ones and the origin of
+//! every value is tracked, so it is possible to tell where it comes from.
+use crate::diff::flatten;
+use crate::install_settings::InstallSettings;
+use crate::settings::{
+    ArgValue, MergeStrategy, SettingDescription, SettingValue, Settings, SettingsError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Prefix of the environment variables which override the settings
+pub const ENV_PREFIX: &str = "AGAMA_";
+
+/// File which keeps the origins of the settings loaded into the installer
+pub const ORIGINS_PATH: &str = "/run/agama/origins.json";
+
+/// Layer a setting value comes from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// Set in the installer (e.g., using the command line or the web UI)
+    Installer,
+    /// Loaded from a profile
+    Profile,
+    /// Given in the kernel command line
+    Cmdline,
+    /// Taken from an environment variable
+    Env,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Installer => "installer",
+            Self::Profile => "profile",
+            Self::Cmdline => "cmdline",
+            Self::Env => "env",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Origins of the setting values, by key (e.g., `"software.product"`)
+///
+/// The values whose origin is unknown come from the installer.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Origins(BTreeMap<String, Origin>);
+
+impl Origins {
+    /// Reads the origins from a file, returning no origins if it does not exist
+    pub fn read(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the origins to a file, creating its directory if needed
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// Returns the origin of the value of the given setting
+    pub fn get(&self, key: &str) -> Origin {
+        self.0.get(key).copied().unwrap_or(Origin::Installer)
+    }
+
+    /// Records that the given settings come from `origin`
+    pub fn set<'a>(&mut self, keys: impl IntoIterator<Item = &'a str>, origin: Origin) {
+        for key in keys {
+            match origin {
+                Origin::Installer => self.0.remove(key),
+                origin => self.0.insert(key.to_string(), origin),
+            };
+        }
+    }
+
+    /// Adds the origins from `other`, which take precedence
+    pub fn extend(&mut self, other: Origins) {
+        self.0.extend(other.0);
+    }
+}
+
+/// Settings built from several layers
+#[derive(Debug, Default)]
+pub struct LayeredSettings {
+    pub settings: InstallSettings,
+    pub origins: Origins,
+}
+
+impl LayeredSettings {
+    /// Adds a layer on top of the current ones
+    ///
+    /// The collections of the layer are merged according to `strategy` (see
+    /// [Settings::merge_with]).
+    pub fn add_layer(
+        &mut self,
+        layer: &InstallSettings,
+        origin: Origin,
+        strategy: Option<MergeStrategy>,
+    ) {
+        self.settings.merge_with(layer, strategy);
+        let values = defined_values(layer);
+        self.origins.set(values.keys().map(String::as_str), origin);
+    }
+}
+
+/// Returns the defined values of the given settings, by key
+///
+/// Empty collections are considered as not defined.
+pub fn defined_values(settings: &InstallSettings) -> BTreeMap<String, Value> {
+    let mut values = flatten(settings);
+    values.retain(|_, value| !matches!(value, Value::Array(items) if items.is_empty()));
+    values
+}
+
+/// Returns the name of the environment variable which overrides a setting
+///
+/// ```
+/// # use agama_lib::install_settings::InstallSettings;
+/// # use agama_lib::layers::env_var_name;
+/// let setting = InstallSettings::find_setting("user.userName").unwrap();
+/// assert_eq!(env_var_name(&setting), "AGAMA_USER_USER_NAME");
+/// ```
+pub fn env_var_name(setting: &SettingDescription) -> String {
+    format!(
+        "{ENV_PREFIX}{}",
+        setting.attr.replace('.', "_").to_uppercase()
+    )
+}
+
+/// Builds the settings defined by the given environment variables
+///
+/// Only the variables which correspond to a setting (see [env_var_name]) are considered. The
+/// collections (e.g., `AGAMA_STORAGE_DEVICES`) are written in JSON.
+pub fn env_settings(
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<InstallSettings, SettingsError> {
+    let known = InstallSettings::settings();
+    let mut settings = InstallSettings::default();
+    for (name, value) in vars {
+        let Some(setting) = known.iter().find(|s| env_var_name(s) == name) else {
+            continue;
+        };
+
+        set_text_value(&mut settings, setting, value)?;
+    }
+    Ok(settings)
+}
+
+/// Sets the value of a setting given as text, which is written in JSON for collections
+pub(crate) fn set_text_value(
+    settings: &mut InstallSettings,
+    setting: &SettingDescription,
+    value: String,
+) -> Result<(), SettingsError> {
+    if setting.collection {
+        let elements = ArgValue::Text(value)
+            .to_setting_objects()
+            .map_err(|e| SettingsError::from(e).in_section(&setting.key))?;
+        settings.unset(&setting.attr)?;
+        for element in elements {
+            settings.add(&setting.attr, element)?;
+        }
+        Ok(())
+    } else {
+        settings.set(&setting.attr, SettingValue(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{masked, MASKED_SECRET};
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_settings() {
+        let settings = env_settings(vars(&[
+            ("AGAMA_SOFTWARE_PRODUCT", "Tumbleweed"),
+            ("AGAMA_STORAGE_LVM", "true"),
+            ("AGAMA_STORAGE_DEVICES", r#"[{"name": "/dev/sda"}]"#),
+            ("AGAMA_UNKNOWN", "ignored"),
+            ("HOME", "/root"),
+        ]))
+        .unwrap();
+
+        assert_eq!(settings.software.unwrap().product.unwrap(), "Tumbleweed");
+        let storage = settings.storage.unwrap();
+        assert_eq!(storage.lvm, Some(true));
+        assert_eq!(storage.devices[0].name.as_deref(), Some("/dev/sda"));
+
+        let error = env_settings(vars(&[("AGAMA_STORAGE_LVM", "maybe")])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Wrong value for 'storage.lvm': not a valid boolean: 'maybe'"
+        );
+    }
+
+    #[test]
+    fn test_layers() {
+        let profile = env_settings(vars(&[
+            ("AGAMA_SOFTWARE_PRODUCT", "ALP"),
+            ("AGAMA_USER_USER_NAME", "jane"),
+        ]))
+        .unwrap();
+        let env = env_settings(vars(&[("AGAMA_SOFTWARE_PRODUCT", "Tumbleweed")])).unwrap();
+
+        let mut layers = LayeredSettings::default();
+        layers.add_layer(&profile, Origin::Profile, None);
+        layers.add_layer(&env, Origin::Env, None);
+
+        let software = layers.settings.software.as_ref().unwrap();
+        assert_eq!(software.product.as_deref(), Some("Tumbleweed"));
+        assert_eq!(layers.origins.get("software.product"), Origin::Env);
+        assert_eq!(layers.origins.get("user.userName"), Origin::Profile);
+        assert_eq!(layers.origins.get("storage.devices"), Origin::Installer);
+    }
+
+    #[test]
+    fn test_defined_values_masked() {
+        let settings = env_settings(vars(&[
+            ("AGAMA_USER_PASSWORD", "s3cr3t"),
+            ("AGAMA_ROOT_PASSWORD", "r00t"),
+            ("AGAMA_USER_USER_NAME", "jane"),
+        ]))
+        .unwrap();
+        let values = defined_values(&masked(&settings));
+        assert_eq!(values["user.password"], MASKED_SECRET);
+        assert_eq!(values["root.password"], MASKED_SECRET);
+        assert_eq!(values["user.userName"], "jane");
+    }
+
+    #[test]
+    fn test_write_origins() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agama/origins.json");
+        let mut origins = Origins::default();
+        origins.set(["software.product"], Origin::Profile);
+
+        origins.write(&path).unwrap();
+        assert_eq!(Origins::read(&path).unwrap(), origins);
+    }
+}
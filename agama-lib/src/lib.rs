@@ -1,5 +1,10 @@
+pub mod bootloader;
+pub mod cmdline;
+pub mod diff;
 pub mod error;
 pub mod install_settings;
+pub mod layers;
+pub mod localization;
 pub mod manager;
 pub mod profile;
 pub mod settings;
@@ -0,0 +1,83 @@
+use super::proxies::{Language1Proxy, Locale1Proxy};
+use crate::error::ServiceError;
+use serde::Serialize;
+use zbus::Connection;
+
+/// Represents a language
+#[derive(Debug, Serialize)]
+pub struct Language {
+    /// Language ID (e.g., "en_US")
+    pub id: String,
+    /// Language name (e.g., "English (US)")
+    pub name: String,
+}
+
+/// Represents a keyboard layout
+#[derive(Debug, Serialize)]
+pub struct Keymap {
+    /// Keymap ID (e.g., "de-nodeadkeys")
+    pub id: String,
+    /// Keymap description (e.g., "German (no dead keys)")
+    pub description: String,
+}
+
+/// D-Bus client for the language service
+pub struct LocalizationClient<'a> {
+    language_proxy: Language1Proxy<'a>,
+    locale_proxy: Locale1Proxy<'a>,
+}
+
+impl<'a> LocalizationClient<'a> {
+    pub async fn new(connection: Connection) -> Result<LocalizationClient<'a>, ServiceError> {
+        Ok(Self {
+            language_proxy: Language1Proxy::new(&connection).await?,
+            locale_proxy: Locale1Proxy::new(&connection).await?,
+        })
+    }
+
+    /// Returns the available languages
+    pub async fn languages(&self) -> Result<Vec<Language>, ServiceError> {
+        let languages = self
+            .language_proxy
+            .available_languages()
+            .await?
+            .into_iter()
+            .map(|(id, name, _)| Language { id, name })
+            .collect();
+        Ok(languages)
+    }
+
+    /// Returns the language selected for the installed system
+    pub async fn language(&self) -> Result<Option<String>, ServiceError> {
+        let languages = self.language_proxy.marked_for_install().await?;
+        Ok(languages.into_iter().next())
+    }
+
+    /// Selects the language for the installed system
+    pub async fn select_language(&self, language_id: &str) -> Result<(), ServiceError> {
+        Ok(self.language_proxy.to_install(&[language_id]).await?)
+    }
+
+    /// Returns the available keyboard layouts
+    pub async fn keymaps(&self) -> Result<Vec<Keymap>, ServiceError> {
+        let keymaps = self
+            .locale_proxy
+            .list_keymaps()
+            .await?
+            .into_iter()
+            .map(|(id, description)| Keymap { id, description })
+            .collect();
+        Ok(keymaps)
+    }
+
+    /// Returns the keyboard layout of the installed system, if it is set
+    pub async fn keymap(&self) -> Result<Option<String>, ServiceError> {
+        let keymap = self.locale_proxy.keymap().await?;
+        Ok(Some(keymap).filter(|k| !k.is_empty()))
+    }
+
+    /// Sets the keyboard layout of the installed system
+    pub async fn set_keymap(&self, keymap_id: &str) -> Result<(), ServiceError> {
+        Ok(self.locale_proxy.set_keymap(keymap_id).await?)
+    }
+}
@@ -1,36 +1,163 @@
+pub mod autoyast;
+mod locator;
+
+pub use locator::Location;
+
 use crate::error::ProfileError;
+use crate::install_settings::InstallSettings;
 use curl::easy::Easy;
 use jsonschema::JSONSchema;
+use schemars::gen::SchemaSettings;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json;
-use std::{
-    fs, io,
-    io::{stdout, Write},
-    path::Path,
-    process::Command,
-};
+use std::{fs, io, io::Write, path::Path, process::Command, str::FromStr};
 use tempfile::tempdir;
 
-/// Downloads a file a writes it to the stdout()
+/// Downloads a file and writes it to the given writer
 ///
 /// TODO: move this code to a struct
 /// TODO: add support for YaST-specific URLs
-/// TODO: do not write to stdout, but to something implementing the Write trait
 /// TODO: retry the download if it fails
-pub fn download(url: &str) -> Result<(), ProfileError> {
+pub fn download(url: &str, mut out: impl Write) -> Result<(), ProfileError> {
     let mut easy = Easy::new();
     easy.url(url)?;
-    easy.write_function(|data| {
-        stdout().write_all(data).unwrap();
-        Ok(data.len())
-    })?;
-    easy.perform()?;
-    Ok(())
+    let mut write_error = None;
+    let result = {
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            // returning less bytes than the received ones aborts the transfer
+            match out.write_all(data) {
+                Ok(()) => Ok(data.len()),
+                Err(error) => {
+                    write_error = Some(error);
+                    Ok(0)
+                }
+            }
+        })?;
+        transfer.perform()
+    };
+    if let Some(error) = write_error {
+        return Err(ProfileError::InputOutputError(error));
+    }
+    Ok(result?)
+}
+
+/// Supported profile formats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProfileFormat {
+    Json,
+    Yaml,
+    /// Jsonnet profiles must be evaluated (see [ProfileEvaluator]) before using them
+    Jsonnet,
+}
+
+impl ProfileFormat {
+    /// Guesses the format from the file extension
+    ///
+    /// It works with URLs too (e.g., `http://example.net/profile.yaml`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "jsonnet" => Some(Self::Jsonnet),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the contents of the profile
+    ///
+    /// Anything which is neither a JSON document nor a YAML mapping is considered jsonnet. An
+    /// object which is not valid JSON is jsonnet too, even if it could be read as YAML.
+    pub fn from_contents(contents: &str) -> Self {
+        if serde_json::from_str::<serde_json::Value>(contents).is_ok() {
+            return Self::Json;
+        }
+        if contents.trim_start().starts_with('{') {
+            return Self::Jsonnet;
+        }
+
+        match serde_yaml::from_str::<serde_yaml::Value>(contents) {
+            Ok(serde_yaml::Value::Mapping(_)) => Self::Yaml,
+            _ => Self::Jsonnet,
+        }
+    }
+
+    /// Guesses the format from the file extension, falling back to the contents
+    pub fn detect(path: &Path, contents: &str) -> Self {
+        Self::from_path(path).unwrap_or_else(|| Self::from_contents(contents))
+    }
+
+    /// Deserializes a profile written in this format
+    ///
+    /// ```
+    /// # use agama_lib::install_settings::InstallSettings;
+    /// # use agama_lib::profile::ProfileFormat;
+    /// let profile = "software:\n  product: Tumbleweed\n";
+    /// let settings: InstallSettings = ProfileFormat::Yaml.parse(profile).unwrap();
+    /// assert_eq!(settings.software.unwrap().product.unwrap(), "Tumbleweed");
+    /// ```
+    pub fn parse<T: DeserializeOwned>(&self, contents: &str) -> Result<T, ProfileError> {
+        match self {
+            Self::Json => Ok(serde_json::from_str(contents)?),
+            Self::Yaml => Ok(serde_yaml::from_str(contents)?),
+            Self::Jsonnet => Err(ProfileError::NotEvaluated),
+        }
+    }
+}
+
+/// Reads a JSON or YAML profile from a file, detecting its format
+pub fn read_profile<T: DeserializeOwned>(path: &Path) -> Result<T, ProfileError> {
+    let contents = fs::read_to_string(path)?;
+    ProfileFormat::detect(path, &contents).parse(&contents)
 }
 
-#[derive(Debug)]
+/// Returns the JSON Schema for the profiles
+///
+/// The schema is generated from the [InstallSettings] model, so both are always in sync. The
+/// `share/profile.schema.json` file is a copy of this schema for other tools to use.
+pub fn profile_schema() -> serde_json::Value {
+    let generator = SchemaSettings::draft07()
+        .with(|s| {
+            s.option_add_null_type = false;
+            s.inline_subschemas = true;
+        })
+        .into_generator();
+    let schema = generator.into_root_schema_for::<InstallSettings>();
+    let mut schema = serde_json::to_value(schema).expect("a serializable schema");
+    // serde does not support denying unknown fields when there are flattened ones
+    schema["additionalProperties"] = false.into();
+    schema["$id"] = "https://github.com/d-installer/profile.schema.json".into();
+    schema
+}
+
+/// Result of validating a profile
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", content = "errors", rename_all = "camelCase")]
 pub enum ValidationResult {
     Valid,
-    NotValid(Vec<String>),
+    NotValid(Vec<ValidationError>),
+}
+
+impl ValidationResult {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+}
+
+/// Validation error found in a profile
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    /// JSON pointer to the wrong value (e.g., "/storage/devices/0")
+    pub instance_path: String,
+    /// JSON pointer to the schema keyword which failed (e.g., "/properties/user/required")
+    pub schema_path: String,
+    /// Human-readable error message
+    pub message: String,
+    /// Location of the wrong value in the profile, if known
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
 }
 
 /// Checks whether an autoinstallation profile is valid
@@ -47,47 +174,191 @@ pub enum ValidationResult {
 ///   { "product": { "name": "Tumbleweed" } }
 /// "#;
 /// let result = validator.validate_str(&wrong_profile).unwrap();
-/// assert!(matches!(ValidationResult::NotValid, result));
+/// let ValidationResult::NotValid(errors) = result else {
+///   panic!("the profile should not be valid");
+/// };
+/// assert_eq!(errors[0].instance_path, "");
+/// assert_eq!(errors[0].location.unwrap().line, 2);
 ///
 /// // or a file
-/// validator.validate_file(Path::new("share/examples/profile.json"));
-/// assert!(matches!(ValidationResult::Valid, result));
+/// let result = validator.validate_file(Path::new("share/examples/profile.json")).unwrap();
+/// assert!(result.is_valid());
 /// ```
 pub struct ProfileValidator {
     schema: JSONSchema,
 }
 
 impl ProfileValidator {
+    /// Builds a validator which uses the schema generated from the model (see [profile_schema])
     pub fn default_schema() -> Result<Self, ProfileError> {
-        let relative_path = Path::new("agama-lib/share/profile.schema.json");
-        let path = if relative_path.exists() {
-            relative_path
-        } else {
-            Path::new("/usr/share/agama-cli/profile.schema.json")
-        };
-        Self::new(path)
+        Ok(Self::from_schema(&profile_schema()))
     }
 
+    /// Builds a validator which uses the schema from the given file
     pub fn new(schema_path: &Path) -> Result<Self, ProfileError> {
         let contents = fs::read_to_string(schema_path)?;
         let schema = serde_json::from_str(&contents)?;
-        let schema = JSONSchema::compile(&schema).expect("A valid schema");
-        Ok(Self { schema })
+        Ok(Self::from_schema(&schema))
+    }
+
+    fn from_schema(schema: &serde_json::Value) -> Self {
+        let schema = JSONSchema::compile(schema).expect("A valid schema");
+        Self { schema }
     }
 
+    /// Validates a JSON or YAML file, detecting its format from the extension or the contents
     pub fn validate_file(&self, profile_path: &Path) -> Result<ValidationResult, ProfileError> {
         let contents = fs::read_to_string(profile_path)?;
-        self.validate_str(&contents)
+        let format = ProfileFormat::detect(profile_path, &contents);
+        self.validate_with_format(&contents, format)
     }
 
+    /// Validates a JSON or YAML profile, detecting its format from the contents
     pub fn validate_str(&self, profile: &str) -> Result<ValidationResult, ProfileError> {
-        let contents = serde_json::from_str(profile)?;
-        let result = self.schema.validate(&contents);
+        self.validate_with_format(profile, ProfileFormat::from_contents(profile))
+    }
+
+    /// Validates a profile written in the given format
+    ///
+    /// The location of the errors refers to the lines and columns of the original profile.
+    pub fn validate_with_format(
+        &self,
+        profile: &str,
+        format: ProfileFormat,
+    ) -> Result<ValidationResult, ProfileError> {
+        let contents: serde_json::Value = format.parse(profile)?;
+        let locate = match format {
+            ProfileFormat::Yaml => locator::locate_yaml,
+            _ => locator::locate_json,
+        };
+        Ok(self.validate_and_locate(&contents, |path| locate(profile, path)))
+    }
+
+    /// Validates an already parsed profile
+    ///
+    /// As the original source is unknown, the errors do not include any location.
+    pub fn validate_value(&self, profile: &serde_json::Value) -> ValidationResult {
+        self.validate_and_locate(profile, |_| None)
+    }
+
+    fn validate_and_locate(
+        &self,
+        profile: &serde_json::Value,
+        locate: impl Fn(&[String]) -> Option<Location>,
+    ) -> ValidationResult {
+        let result = self.schema.validate(profile);
         if let Err(errors) = result {
-            let messages: Vec<String> = errors.map(|e| format!("{e}")).collect();
-            return Ok(ValidationResult::NotValid(messages));
+            let errors = errors
+                .map(|e| ValidationError {
+                    message: format!("{e}"),
+                    schema_path: e.schema_path.to_string(),
+                    instance_path: e.instance_path.to_string(),
+                    location: locate(&e.instance_path.into_vec()),
+                })
+                .collect();
+            return ValidationResult::NotValid(errors);
         }
-        Ok(ValidationResult::Valid)
+        ValidationResult::Valid
+    }
+}
+
+/// How to handle the secrets (e.g., passwords) when exporting a profile
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SecretsPolicy {
+    /// Keep the secrets as they are
+    #[default]
+    Keep,
+    /// Remove the secrets from the profile
+    Omit,
+    /// Replace the secrets with [SECRET_PLACEHOLDER]
+    Placeholder,
+}
+
+impl FromStr for SecretsPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "omit" => Ok(Self::Omit),
+            "placeholder" => Ok(Self::Placeholder),
+            _ => Err("Unknown secrets policy"),
+        }
+    }
+}
+
+/// Value which replaces the secrets when using [SecretsPolicy::Placeholder]
+///
+/// It is a reference to a secret (see [secret_reference](crate::settings::ValueSource::secret_reference)), so the user is asked for
+/// the value when loading the profile.
+pub const SECRET_PLACEHOLDER: &str = "@prompt";
+
+/// Builds a reusable profile from the given settings
+///
+/// Empty values (e.g., a product which is not selected yet) are removed and the result is
+/// validated against the profile schema, so it can be loaded later.
+///
+/// ```
+/// # use agama_lib::install_settings::{InstallSettings, SoftwareSettings, StorageSettings};
+/// # use agama_lib::profile::{export_profile, SecretsPolicy};
+/// let settings = InstallSettings {
+///   software: Some(SoftwareSettings { product: Some("".to_string()), ..Default::default() }),
+///   storage: Some(StorageSettings {
+///     lvm: Some(true),
+///     encryption_password: Some("nots3cr3t".to_string()),
+///     ..Default::default()
+///   }),
+///   ..Default::default()
+/// };
+/// let profile = export_profile(settings, SecretsPolicy::Placeholder).unwrap();
+/// assert_eq!(
+///   profile.to_string(),
+///   r#"{"storage":{"encryptionPassword":"@prompt","lvm":true}}"#
+/// );
+/// ```
+pub fn export_profile(
+    mut settings: InstallSettings,
+    secrets: SecretsPolicy,
+) -> Result<serde_json::Value, ProfileError> {
+    for (_, secret) in settings.secrets_mut() {
+        match secrets {
+            SecretsPolicy::Keep => {}
+            SecretsPolicy::Omit => *secret = None,
+            SecretsPolicy::Placeholder => {
+                if secret.is_some() {
+                    *secret = Some(SECRET_PLACEHOLDER.to_string())
+                }
+            }
+        }
+    }
+
+    let mut profile = settings.to_profile();
+    remove_empty_values(&mut profile);
+
+    let validator = ProfileValidator::default_schema()?;
+    if let ValidationResult::NotValid(errors) = validator.validate_value(&profile) {
+        let messages = errors.into_iter().map(|e| e.message).collect();
+        return Err(ProfileError::InvalidExport(messages));
+    }
+    Ok(profile)
+}
+
+/// Removes nulls, empty strings, empty arrays and empty objects
+///
+/// It returns whether the value itself is empty.
+fn remove_empty_values(value: &mut serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::String(s) => s.is_empty(),
+        serde_json::Value::Array(items) => {
+            items.retain_mut(|v| !remove_empty_values(v));
+            items.is_empty()
+        }
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !remove_empty_values(v));
+            map.is_empty()
+        }
+        _ => false,
     }
 }
 
@@ -99,11 +370,18 @@ impl ProfileValidator {
 pub struct ProfileEvaluator {}
 
 impl ProfileEvaluator {
-    pub fn evaluate(&self, profile_path: &Path) -> Result<(), ProfileError> {
+    /// Evaluates the jsonnet profile, writing the resulting JSON to the given writer
+    pub fn evaluate(&self, profile_path: &Path, out: impl Write) -> Result<(), ProfileError> {
+        let profile = fs::read_to_string(profile_path)?;
+        self.evaluate_str(&profile, out)
+    }
+
+    /// Evaluates the given jsonnet code, writing the resulting JSON to the given writer
+    pub fn evaluate_str(&self, profile: &str, mut out: impl Write) -> Result<(), ProfileError> {
         let dir = tempdir()?;
 
         let working_path = dir.path().join("profile.jsonnet");
-        fs::copy(profile_path, working_path)?;
+        fs::write(working_path, profile)?;
 
         let hwinfo_path = dir.path().join("hw.libsonnet");
         self.write_hwinfo(&hwinfo_path)
@@ -114,7 +392,7 @@ impl ProfileEvaluator {
             .current_dir(&dir)
             .output()
             .map_err(ProfileError::EvaluationError)?;
-        io::stdout().write_all(&result.stdout)?;
+        out.write_all(&result.stdout)?;
         Ok(())
     }
 
@@ -127,9 +405,90 @@ impl ProfileEvaluator {
             .args(["-json", "-class", "disk"])
             .output()?;
         let mut file = fs::File::create(path)?;
-        file.write(b"{ \"disks\":\n")?;
+        file.write_all(b"{ \"disks\":\n")?;
         file.write_all(&result.stdout)?;
-        file.write(b"\n}")?;
+        file.write_all(b"\n}")?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_schema() {
+        let contents = fs::read_to_string("share/profile.schema.json").unwrap();
+        let shipped: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(
+            shipped,
+            profile_schema(),
+            "share/profile.schema.json is outdated, regenerate it with 'agama profile schema'"
+        );
+    }
+
+    #[test]
+    fn test_format_detection() {
+        let path = Path::new("http://example.net/profile.yml");
+        assert_eq!(ProfileFormat::from_path(path), Some(ProfileFormat::Yaml));
+        assert_eq!(ProfileFormat::from_path(Path::new("profile")), None);
+
+        let json = r#"{ "software": { "product": "ALP" } }"#;
+        assert_eq!(ProfileFormat::from_contents(json), ProfileFormat::Json);
+        let yaml = "software:\n  product: ALP\n";
+        assert_eq!(ProfileFormat::from_contents(yaml), ProfileFormat::Yaml);
+        let jsonnet = "local agama = import 'hw.libsonnet';\n{ software: { product: 'ALP' } }";
+        assert_eq!(
+            ProfileFormat::from_contents(jsonnet),
+            ProfileFormat::Jsonnet
+        );
+        let jsonnet = "{ software: { product: 'ALP' } }";
+        assert_eq!(
+            ProfileFormat::from_contents(jsonnet),
+            ProfileFormat::Jsonnet
+        );
+    }
+
+    #[test]
+    fn test_download_write_error() {
+        struct FullDisk;
+
+        impl Write for FullDisk {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("no space left on device"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        fs::write(&path, "{}").unwrap();
+        let url = format!("file://{}", path.display());
+        let mut contents = vec![];
+        download(&url, &mut contents).unwrap();
+        assert_eq!(contents, b"{}");
+
+        let error = download(&url, FullDisk).unwrap_err();
+        assert!(matches!(error, ProfileError::InputOutputError(_)));
+    }
+
+    #[test]
+    fn test_validate_yaml() {
+        let validator = ProfileValidator::new(Path::new("share/profile.schema.json")).unwrap();
+        let profile = "software:\n  product: ALP\nroot:\n  password: 1234\n";
+        let ValidationResult::NotValid(errors) = validator.validate_str(profile).unwrap() else {
+            panic!("the profile should not be valid");
+        };
+        assert_eq!(errors[0].instance_path, "/root/password");
+        assert_eq!(
+            errors[0].location,
+            Some(Location {
+                line: 4,
+                column: 13
+            })
+        );
+    }
+}
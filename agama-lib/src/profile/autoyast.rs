@@ -0,0 +1,430 @@
+//! Conversion of AutoYaST profiles
+//!
+//! This module converts the supported parts of an AutoYaST XML profile into Agama installation
+//! settings. AutoYaST offers many more options than Agama, so the conversion keeps track of the
+//! sections (and elements) which are dropped, allowing the user to review them.
+//!
+//! ```
+//! # use agama_lib::profile::autoyast::convert;
+//! let xml = r#"<?xml version="1.0"?>
+//! <profile xmlns="http://www.suse.com/1.0/yast2ns"
+//!   xmlns:config="http://www.suse.com/1.0/configns">
+//!   <software>
+//!     <products config:type="list"><product>Tumbleweed</product></products>
+//!   </software>
+//!   <networking><keep_install_network config:type="boolean">true</keep_install_network></networking>
+//! </profile>"#;
+//! let conversion = convert(xml).unwrap();
+//! let software = conversion.settings.software.unwrap();
+//! assert_eq!(software.product.unwrap(), "Tumbleweed");
+//! assert_eq!(conversion.dropped, vec!["networking"]);
+//! ```
+use crate::error::ProfileError;
+use crate::install_settings::{
+    Device, FirstUserSettings, InstallSettings, LocalizationSettings, RootUserSettings,
+    SoftwareSettings, StorageSettings, UserSettings,
+};
+use roxmltree::{Document, Node};
+
+/// Result of converting an AutoYaST profile
+#[derive(Debug)]
+pub struct AutoyastConversion {
+    /// Installation settings
+    pub settings: InstallSettings,
+    /// Paths of the AutoYaST sections/elements which were not converted (e.g.,
+    /// `"software/remove-packages"`)
+    pub dropped: Vec<String>,
+}
+
+/// Converts an AutoYaST profile into installation settings
+///
+/// * `xml`: AutoYaST profile.
+pub fn convert(xml: &str) -> Result<AutoyastConversion, ProfileError> {
+    let document = Document::parse(xml)?;
+    let mut converter = Converter::default();
+    converter.convert(document.root_element());
+    Ok(converter.into())
+}
+
+#[derive(Default)]
+struct Converter {
+    settings: InstallSettings,
+    dropped: Vec<String>,
+    autologin_user: Option<String>,
+}
+
+impl From<Converter> for AutoyastConversion {
+    fn from(converter: Converter) -> Self {
+        Self {
+            settings: converter.settings,
+            dropped: converter.dropped,
+        }
+    }
+}
+
+impl Converter {
+    fn convert(&mut self, profile: Node) {
+        for section in elements(profile) {
+            let name = section.tag_name().name();
+            match name {
+                "users" => self.convert_users(section),
+                "login_settings" => self.convert_login_settings(section),
+                "software" => self.convert_software(section),
+                "partitioning" => self.convert_partitioning(section),
+                "language" => self.convert_language(section),
+                "keyboard" => self.convert_keyboard(section),
+                _ => self.drop(name),
+            }
+        }
+
+        let autologin_user = self.autologin_user.take();
+        if let Some(user) = self.first_user_mut() {
+            if autologin_user.is_some() && user.user_name == autologin_user {
+                user.autologin = Some(true);
+            }
+        }
+    }
+
+    fn convert_users(&mut self, users: Node) {
+        for user in elements(users) {
+            let user_name = child_text(user, "username").unwrap_or_default();
+            let path = format!("users/user[{}]", user_name);
+            if user_name == "root" {
+                self.convert_root_user(user, &path);
+            } else if self.first_user_mut().is_none() {
+                self.convert_first_user(user, &path);
+            } else {
+                self.drop(&path);
+            }
+        }
+    }
+
+    fn convert_root_user(&mut self, user: Node, path: &str) {
+        let mut root = RootUserSettings::default();
+        for element in elements(user) {
+            let name = element.tag_name().name();
+            match name {
+                "username" | "encrypted" => {}
+                "user_password" if !is_true(user, "encrypted") => root.password = text(element),
+                "authorized_keys" => {
+                    for (index, key) in elements(element).enumerate() {
+                        if index == 0 {
+                            root.ssh_public_key = text(key);
+                        } else {
+                            self.drop(&format!("{path}/{name}[{index}]"));
+                        }
+                    }
+                }
+                _ => self.drop(&format!("{path}/{name}")),
+            }
+        }
+        self.user_settings().root = Some(root);
+    }
+
+    fn convert_first_user(&mut self, user: Node, path: &str) {
+        let mut first_user = FirstUserSettings::default();
+        for element in elements(user) {
+            let name = element.tag_name().name();
+            let text = text(element);
+            match name {
+                "encrypted" => {}
+                "username" => first_user.user_name = text,
+                "fullname" => first_user.full_name = text,
+                "user_password" if !is_true(user, "encrypted") => first_user.password = text,
+                _ => self.drop(&format!("{path}/{name}")),
+            }
+        }
+        self.user_settings().first_user = Some(first_user);
+    }
+
+    fn convert_login_settings(&mut self, login_settings: Node) {
+        for element in elements(login_settings) {
+            let name = element.tag_name().name();
+            match name {
+                "autologin_user" => self.autologin_user = text(element),
+                _ => self.drop(&format!("login_settings/{name}")),
+            }
+        }
+    }
+
+    fn convert_software(&mut self, software: Node) {
+        let mut settings = SoftwareSettings::default();
+        for element in elements(software) {
+            let name = element.tag_name().name();
+            match name {
+                "products" => {
+                    for (index, product) in elements(element).enumerate() {
+                        if index == 0 {
+                            settings.product = text(product);
+                        } else {
+                            self.drop(&format!("software/{name}[{index}]"));
+                        }
+                    }
+                }
+                "patterns" => settings.patterns = Some(list_entries(element)),
+                "packages" => settings.packages = Some(list_entries(element)),
+                _ => self.drop(&format!("software/{name}")),
+            }
+        }
+        self.settings.software = Some(settings);
+    }
+
+    fn convert_partitioning(&mut self, partitioning: Node) {
+        let mut settings = StorageSettings::default();
+        for (index, drive) in elements(partitioning).enumerate() {
+            let path = format!("partitioning/drive[{index}]");
+            let lvm = child_text(drive, "type").as_deref() == Some("CT_LVM");
+            if lvm {
+                settings.lvm = Some(true);
+            }
+
+            for element in elements(drive) {
+                let name = element.tag_name().name();
+                match (name, text(element)) {
+                    ("type", _) => {}
+                    // the device of a CT_LVM drive is the volume group, not a disk
+                    ("device", Some(device)) if !lvm => {
+                        settings.devices.push(Device::with_name(&device))
+                    }
+                    _ => self.drop(&format!("{path}/{name}")),
+                }
+            }
+        }
+        self.settings.storage = Some(settings);
+    }
+
+    fn convert_language(&mut self, language: Node) {
+        for element in elements(language) {
+            let name = element.tag_name().name();
+            match name {
+                "language" => self.localization_settings().language = text(element),
+                _ => self.drop(&format!("language/{name}")),
+            }
+        }
+    }
+
+    fn convert_keyboard(&mut self, keyboard: Node) {
+        for element in elements(keyboard) {
+            let name = element.tag_name().name();
+            match (name, text(element)) {
+                ("keymap", Some(keymap)) => {
+                    self.localization_settings().keyboard = Some(keymap_id(&keymap))
+                }
+                _ => self.drop(&format!("keyboard/{name}")),
+            }
+        }
+    }
+
+    fn localization_settings(&mut self) -> &mut LocalizationSettings {
+        self.settings.localization.get_or_insert(Default::default())
+    }
+
+    fn user_settings(&mut self) -> &mut UserSettings {
+        self.settings.user.get_or_insert(Default::default())
+    }
+
+    fn first_user_mut(&mut self) -> Option<&mut FirstUserSettings> {
+        self.settings.user.as_mut()?.first_user.as_mut()
+    }
+
+    fn drop(&mut self, path: &str) {
+        self.dropped.push(path.to_string());
+    }
+}
+
+/// Returns the keymap ID for an AutoYaST keyboard name (e.g., "de-nodeadkeys" for "german")
+///
+/// AutoYaST accepts the keymap IDs too, so unknown names are kept as they are.
+fn keymap_id(name: &str) -> String {
+    let id = match name {
+        "english-us" => "us",
+        "english-uk" => "gb",
+        "german" => "de-nodeadkeys",
+        "german-deadkey" => "de",
+        "german-ch" => "ch",
+        "french" => "fr",
+        "french-ch" => "ch-fr",
+        "spanish" => "es",
+        "italian" => "it",
+        "portugese" => "pt",
+        "dutch" => "nl",
+        "czech" => "cz",
+        "polish" => "pl",
+        "russian" => "ru",
+        "japanese" => "jp",
+        _ => name,
+    };
+    id.to_string()
+}
+
+/// Returns the children elements of a node
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(Node::is_element)
+}
+
+/// Returns the text of an element, without the surrounding whitespace
+///
+/// The profiles are usually indented, so the text may include line breaks and spaces (e.g.,
+/// `<language>\n  en_US\n</language>`). Empty texts are ignored.
+fn text(node: Node) -> Option<String> {
+    node.text()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+/// Returns the text of the first child element with the given name
+fn child_text(node: Node, name: &str) -> Option<String> {
+    elements(node).find(|e| e.has_tag_name(name)).and_then(text)
+}
+
+/// Determines whether the child element with the given name is a true boolean
+fn is_true(node: Node, name: &str) -> bool {
+    child_text(node, name).as_deref() == Some("true")
+}
+
+/// Returns the text of the entries of a list (e.g., `<patterns config:type="list">`)
+fn list_entries(node: Node) -> Vec<String> {
+    elements(node).filter_map(text).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE profile>
+<profile xmlns="http://www.suse.com/1.0/yast2ns" xmlns:config="http://www.suse.com/1.0/configns">
+  <general>
+    <mode><confirm config:type="boolean">false</confirm></mode>
+  </general>
+  <language>
+    <language>de_DE</language>
+    <languages>en_US</languages>
+  </language>
+  <keyboard>
+    <keymap>german</keymap>
+    <keyboard_values><numlock>bios</numlock></keyboard_values>
+  </keyboard>
+  <login_settings><autologin_user>jane</autologin_user></login_settings>
+  <partitioning config:type="list">
+    <drive>
+      <device>/dev/sda</device>
+      <use>all</use>
+    </drive>
+    <drive>
+      <device>/dev/system</device>
+      <type config:type="symbol">CT_LVM</type>
+      <partitions config:type="list"/>
+    </drive>
+  </partitioning>
+  <software>
+    <products config:type="list"><product>SLES</product><product>SLED</product></products>
+    <patterns config:type="list"><pattern>base</pattern><pattern>x11</pattern></patterns>
+    <packages config:type="list"><package>vim</package></packages>
+    <remove-packages config:type="list"><package>nano</package></remove-packages>
+  </software>
+  <users config:type="list">
+    <user>
+      <username>root</username>
+      <user_password>nots3cr3t</user_password>
+      <encrypted config:type="boolean">false</encrypted>
+      <authorized_keys config:type="list">
+        <listentry>ssh-rsa AAAA1</listentry>
+        <listentry>ssh-rsa AAAA2</listentry>
+      </authorized_keys>
+    </user>
+    <user>
+      <username>jane</username>
+      <fullname>Jane Doe</fullname>
+      <user_password>$6$encrypted</user_password>
+      <encrypted config:type="boolean">true</encrypted>
+      <shell>/bin/zsh</shell>
+    </user>
+    <user><username>john</username></user>
+  </users>
+</profile>"#;
+
+    #[test]
+    fn test_convert() {
+        let AutoyastConversion { settings, dropped } = convert(PROFILE).unwrap();
+
+        let user = settings.user.unwrap();
+        let root = user.root.unwrap();
+        assert_eq!(root.password.unwrap(), "nots3cr3t");
+        assert_eq!(root.ssh_public_key.unwrap(), "ssh-rsa AAAA1");
+        let first_user = user.first_user.unwrap();
+        assert_eq!(first_user.user_name.unwrap(), "jane");
+        assert_eq!(first_user.full_name.unwrap(), "Jane Doe");
+        assert_eq!(first_user.password, None);
+        assert_eq!(first_user.autologin, Some(true));
+
+        let software = settings.software.unwrap();
+        assert_eq!(software.product.unwrap(), "SLES");
+        assert_eq!(software.patterns.unwrap(), vec!["base", "x11"]);
+        assert_eq!(software.packages.unwrap(), vec!["vim"]);
+
+        let storage = settings.storage.unwrap();
+        assert_eq!(storage.lvm, Some(true));
+        let devices: Vec<_> = storage
+            .devices
+            .iter()
+            .filter_map(|d| d.name.as_deref())
+            .collect();
+        assert_eq!(devices, vec!["/dev/sda"]);
+
+        let localization = settings.localization.unwrap();
+        assert_eq!(localization.language.unwrap(), "de_DE");
+        assert_eq!(localization.keyboard.unwrap(), "de-nodeadkeys");
+
+        assert_eq!(
+            dropped,
+            vec![
+                "general",
+                "language/languages",
+                "keyboard/keyboard_values",
+                "partitioning/drive[0]/use",
+                "partitioning/drive[1]/device",
+                "partitioning/drive[1]/partitions",
+                "software/products[1]",
+                "software/remove-packages",
+                "users/user[root]/authorized_keys[1]",
+                "users/user[jane]/user_password",
+                "users/user[jane]/shell",
+                "users/user[john]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_indented_texts() {
+        let profile = r#"<?xml version="1.0"?>
+<profile xmlns="http://www.suse.com/1.0/yast2ns" xmlns:config="http://www.suse.com/1.0/configns">
+  <language>
+    <language>
+      en_US
+    </language>
+  </language>
+  <software>
+    <products config:type="list">
+      <product>
+        Tumbleweed
+      </product>
+    </products>
+  </software>
+  <partitioning config:type="list">
+    <drive>
+      <device>
+        /dev/sda
+      </device>
+    </drive>
+  </partitioning>
+</profile>"#;
+        let AutoyastConversion { settings, dropped } = convert(profile).unwrap();
+        assert!(dropped.is_empty());
+        assert_eq!(settings.localization.unwrap().language.unwrap(), "en_US");
+        assert_eq!(settings.software.unwrap().product.unwrap(), "Tumbleweed");
+        let devices = settings.storage.unwrap().devices;
+        assert_eq!(devices[0].name.as_deref(), Some("/dev/sda"));
+    }
+}
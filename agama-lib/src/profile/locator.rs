@@ -0,0 +1,355 @@
+//! Finds the position of a value within the source of a profile.
+//!
+//! The JSON Schema validator works on parsed values, so it can only report the path to the wrong
+//! value (e.g., `/storage/devices/0`). This module maps such a path back to a line and a column
+//! in the original file, so users can find the problem quickly. JSON and YAML documents are
+//! supported.
+use serde::Serialize;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::vec::IntoIter;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+/// Position of a value within a file
+///
+/// Both, the line and the column, start at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Returns the location of the value at the given path in a JSON document
+///
+/// Each element of the path is an object key or an array index. An empty path refers to the
+/// root value. It returns `None` if the path cannot be found or the document is malformed.
+///
+/// * `source`: JSON document.
+/// * `path`: path to the value (e.g., `["storage", "devices", "0"]`).
+pub fn locate_json(source: &str, path: &[String]) -> Option<Location> {
+    let mut scanner = JsonScanner::new(source);
+    scanner.skip_whitespace();
+
+    for segment in path {
+        match scanner.peek()? {
+            '{' => {
+                scanner.next();
+                loop {
+                    scanner.skip_whitespace();
+                    let key = scanner.read_string()?;
+                    scanner.skip_whitespace();
+                    scanner.expect(':')?;
+                    scanner.skip_whitespace();
+                    if &key == segment {
+                        break;
+                    }
+                    scanner.skip_value()?;
+                    scanner.skip_whitespace();
+                    scanner.expect(',')?;
+                }
+            }
+            '[' => {
+                let index: usize = segment.parse().ok()?;
+                scanner.next();
+                for _ in 0..index {
+                    scanner.skip_whitespace();
+                    scanner.skip_value()?;
+                    scanner.skip_whitespace();
+                    scanner.expect(',')?;
+                }
+                scanner.skip_whitespace();
+            }
+            _ => return None,
+        }
+    }
+
+    Some(scanner.location())
+}
+
+/// Returns the location of the value at the given path in a YAML document
+///
+/// See [locate_json] for further details.
+///
+/// * `source`: YAML document.
+/// * `path`: path to the value (e.g., `["storage", "devices", "0"]`).
+pub fn locate_yaml(source: &str, path: &[String]) -> Option<Location> {
+    let mut collector = YamlEventCollector::default();
+    Parser::new(source.chars())
+        .load(&mut collector, false)
+        .ok()?;
+    let mut events = collector.events.into_iter().peekable();
+    while matches!(
+        events.peek(),
+        Some((Event::StreamStart | Event::DocumentStart, _))
+    ) {
+        events.next();
+    }
+
+    for segment in path {
+        match events.next()? {
+            (Event::MappingStart(_), _) => loop {
+                let (Event::Scalar(key, ..), _) = events.next()? else {
+                    return None;
+                };
+                if &key == segment {
+                    break;
+                }
+                skip_yaml_node(&mut events)?;
+            },
+            (Event::SequenceStart(_), _) => {
+                let index: usize = segment.parse().ok()?;
+                for _ in 0..index {
+                    skip_yaml_node(&mut events)?;
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    // the position of block mappings is not reliable, so use the first key instead
+    let (event, mut marker) = events.next()?;
+    if let (Event::MappingStart(_), Some((Event::Scalar(..), key_marker))) = (event, events.next())
+    {
+        marker = key_marker;
+    }
+    Some(Location {
+        line: marker.line(),
+        column: marker.col() + 1,
+    })
+}
+
+/// Collects the YAML events and their positions
+#[derive(Default)]
+struct YamlEventCollector {
+    events: Vec<(Event, Marker)>,
+}
+
+impl MarkedEventReceiver for YamlEventCollector {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        self.events.push((event, marker));
+    }
+}
+
+/// Skips the next YAML node, including its children
+///
+/// It returns `None` if the node is not complete (e.g., when it reaches the end of a sequence).
+fn skip_yaml_node(events: &mut Peekable<IntoIter<(Event, Marker)>>) -> Option<()> {
+    let mut depth = 0;
+    loop {
+        match events.next()?.0 {
+            Event::MappingStart(_) | Event::SequenceStart(_) => depth += 1,
+            Event::MappingEnd | Event::SequenceEnd if depth == 0 => return None,
+            Event::MappingEnd | Event::SequenceEnd => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Some(());
+        }
+    }
+}
+
+/// Minimal JSON scanner which keeps track of the current position
+struct JsonScanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn location(&self) -> Location {
+        Location {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let next = self.chars.next()?;
+        if next == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(next)
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        (self.next()? == expected).then_some(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    /// Reads a string, returning its decoded contents
+    ///
+    /// The escape sequences (e.g., `\n` or `\u00e9`) are decoded by serde_json, so the keys can be
+    /// compared with the ones in the path.
+    fn read_string(&mut self) -> Option<String> {
+        self.expect('"')?;
+        let mut raw = String::from('"');
+        loop {
+            match self.next()? {
+                '"' => break,
+                '\\' => {
+                    raw.push('\\');
+                    raw.push(self.next()?);
+                }
+                c => raw.push(c),
+            }
+        }
+        raw.push('"');
+        serde_json::from_str(&raw).ok()
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            '"' => {
+                self.read_string()?;
+            }
+            '{' | '[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        '"' => {
+                            self.read_string()?;
+                            continue;
+                        }
+                        '{' | '[' => depth += 1,
+                        '}' | ']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.next();
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && !matches!(c, ',' | '}' | ']'))
+                {
+                    self.next();
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: &[&str]) -> Vec<String> {
+        segments.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_locate_json() {
+        let source = r#"{
+  "user": { "fullName": "Jane \"JD\" Doe", "userName": 1 },
+  "storage": {
+    "devices": [
+      { "name": "/dev/sda" },
+      { "name": [] }
+    ]
+  }
+}"#;
+        assert_eq!(
+            locate_json(source, &[]),
+            Some(Location { line: 1, column: 1 })
+        );
+        assert_eq!(
+            locate_json(source, &path(&["user", "userName"])),
+            Some(Location {
+                line: 2,
+                column: 56
+            })
+        );
+        assert_eq!(
+            locate_json(source, &path(&["storage", "devices", "1", "name"])),
+            Some(Location {
+                line: 6,
+                column: 17
+            })
+        );
+        assert_eq!(locate_json(source, &path(&["software"])), None);
+    }
+
+    #[test]
+    fn test_locate_json_escaped_keys() {
+        let source = r#"{
+  "a\tb": 1,
+  "caf\u00e9": { "line\nbreak": true, "\ud83d\ude00": "wrong" }
+}"#;
+        assert_eq!(
+            locate_json(source, &path(&["a\tb"])),
+            Some(Location {
+                line: 2,
+                column: 11
+            })
+        );
+        assert_eq!(
+            locate_json(source, &path(&["café", "line\nbreak"])),
+            Some(Location {
+                line: 3,
+                column: 33
+            })
+        );
+        assert_eq!(
+            locate_json(source, &path(&["café", "😀"])),
+            Some(Location {
+                line: 3,
+                column: 55
+            })
+        );
+    }
+
+    #[test]
+    fn test_locate_yaml() {
+        let source = r#"
+user:
+  fullName: Jane Doe
+  userName: 1
+storage:
+  devices:
+    - name: /dev/sda
+    - name:
+        - wrong
+"#;
+        assert_eq!(
+            locate_yaml(source, &path(&["user", "userName"])),
+            Some(Location {
+                line: 4,
+                column: 13
+            })
+        );
+        assert_eq!(
+            locate_yaml(source, &path(&["storage", "devices", "1", "name"])),
+            Some(Location { line: 9, column: 9 })
+        );
+        assert_eq!(
+            locate_yaml(source, &path(&["user"])),
+            Some(Location { line: 3, column: 3 })
+        );
+        assert_eq!(locate_yaml(source, &path(&["storage", "lvm"])), None);
+    }
+}
@@ -128,7 +128,7 @@ impl<'a> ProgressMonitor<'a> {
         Ok(progress)
     }
 
-    async fn build_stream(&self) -> SelectAll<PropertyStream<(u32, String)>> {
+    async fn build_stream(&self) -> SelectAll<PropertyStream<'_, (u32, String)>> {
         let mut streams = vec![];
         for proxy in &self.proxies {
             let s = proxy.receive_current_step_changed().await;
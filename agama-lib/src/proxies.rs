@@ -12,6 +12,7 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 //! Also some proxies can be used against multiple services when they share interface.
+#![allow(clippy::type_complexity)]
 
 use zbus::dbus_proxy;
 
@@ -116,6 +117,22 @@ trait Language1 {
     fn marked_for_install(&self) -> zbus::Result<Vec<String>>;
 }
 
+#[dbus_proxy(
+    interface = "org.opensuse.Agama.Locale1",
+    default_service = "org.opensuse.Agama.Locale1",
+    default_path = "/org/opensuse/Agama/Locale1"
+)]
+trait Locale1 {
+    /// ListKeymaps method
+    fn list_keymaps(&self) -> zbus::Result<Vec<(String, String)>>;
+
+    /// Keymap property
+    #[dbus_proxy(property)]
+    fn keymap(&self) -> zbus::Result<String>;
+    #[dbus_proxy(property)]
+    fn set_keymap(&self, value: &str) -> zbus::Result<()>;
+}
+
 #[dbus_proxy(
     interface = "org.opensuse.Agama.Questions1",
     default_service = "org.opensuse.Agama.Questions1",
@@ -266,6 +283,108 @@ trait Storage1 {
     fn deprecated_system(&self) -> zbus::Result<bool>;
 }
 
+#[dbus_proxy(
+    interface = "org.opensuse.Agama.Storage1.Bootloader",
+    default_service = "org.opensuse.Agama.Storage1",
+    default_path = "/org/opensuse/Agama/Storage1"
+)]
+trait Bootloader {
+    /// SetConfig method
+    fn set_config(
+        &self,
+        settings: std::collections::HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<u32>;
+
+    /// ExtraKernelParams property
+    #[dbus_proxy(property)]
+    fn extra_kernel_params(&self) -> zbus::Result<String>;
+
+    /// SecureBoot property
+    #[dbus_proxy(property)]
+    fn secure_boot(&self) -> zbus::Result<bool>;
+
+    /// Timeout property
+    #[dbus_proxy(property)]
+    fn timeout(&self) -> zbus::Result<u32>;
+
+    /// Type property
+    #[dbus_proxy(property, name = "Type")]
+    fn bootloader_type(&self) -> zbus::Result<String>;
+}
+
+#[dbus_proxy(
+    interface = "org.opensuse.Agama.Storage1.ISCSI.Initiator",
+    default_service = "org.opensuse.Agama.Storage1",
+    default_path = "/org/opensuse/Agama/Storage1"
+)]
+trait ISCSIInitiator {
+    /// Delete method
+    fn delete(&self, node: &zbus::zvariant::ObjectPath<'_>) -> zbus::Result<u32>;
+
+    /// Discover method
+    fn discover(
+        &self,
+        address: &str,
+        port: u32,
+        options: std::collections::HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<u32>;
+
+    /// IBFT property
+    #[dbus_proxy(property, name = "IBFT")]
+    fn ibft(&self) -> zbus::Result<bool>;
+
+    /// InitiatorName property
+    #[dbus_proxy(property)]
+    fn initiator_name(&self) -> zbus::Result<String>;
+    #[dbus_proxy(property)]
+    fn set_initiator_name(&self, value: &str) -> zbus::Result<()>;
+}
+
+/// ISCSINodeProxy is used for each node found by the discovery
+/// (e.g., `/org/opensuse/Agama/Storage1/iscsi_nodes/1`)
+#[dbus_proxy(
+    interface = "org.opensuse.Agama.Storage1.ISCSI.Node",
+    default_service = "org.opensuse.Agama.Storage1"
+)]
+trait ISCSINode {
+    /// Login method
+    fn login(
+        &self,
+        options: std::collections::HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<u32>;
+
+    /// Logout method
+    fn logout(&self) -> zbus::Result<u32>;
+
+    /// Address property
+    #[dbus_proxy(property)]
+    fn address(&self) -> zbus::Result<String>;
+
+    /// Connected property
+    #[dbus_proxy(property)]
+    fn connected(&self) -> zbus::Result<bool>;
+
+    /// IBFT property
+    #[dbus_proxy(property, name = "IBFT")]
+    fn ibft(&self) -> zbus::Result<bool>;
+
+    /// Interface property
+    #[dbus_proxy(property)]
+    fn interface(&self) -> zbus::Result<String>;
+
+    /// Port property
+    #[dbus_proxy(property)]
+    fn port(&self) -> zbus::Result<u32>;
+
+    /// Startup property
+    #[dbus_proxy(property)]
+    fn startup(&self) -> zbus::Result<String>;
+
+    /// Target property
+    #[dbus_proxy(property)]
+    fn target(&self) -> zbus::Result<String>;
+}
+
 #[dbus_proxy(
     interface = "org.opensuse.Agama.Storage1.Proposal.Calculator",
     default_service = "org.opensuse.Agama.Storage1",
@@ -313,10 +432,22 @@ trait StorageProposal {
         &self,
     ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
 
+    /// BootDevice property
+    #[dbus_proxy(property)]
+    fn boot_device(&self) -> zbus::Result<String>;
+
     /// CandidateDevices property
     #[dbus_proxy(property)]
     fn candidate_devices(&self) -> zbus::Result<Vec<String>>;
 
+    /// EncryptionMethod property
+    #[dbus_proxy(property)]
+    fn encryption_method(&self) -> zbus::Result<String>;
+
+    /// EncryptionPBKDFunction property
+    #[dbus_proxy(property, name = "EncryptionPBKDFunction")]
+    fn encryption_pbkd_function(&self) -> zbus::Result<String>;
+
     /// EncryptionPassword property
     #[dbus_proxy(property)]
     fn encryption_password(&self) -> zbus::Result<String>;
@@ -325,6 +456,34 @@ trait StorageProposal {
     #[dbus_proxy(property, name = "LVM")]
     fn lvm(&self) -> zbus::Result<bool>;
 
+    /// LogicalVolumes property
+    #[dbus_proxy(property)]
+    fn logical_volumes(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
+    /// Raids property
+    #[dbus_proxy(property)]
+    fn raids(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
+    /// SpaceActions property
+    #[dbus_proxy(property)]
+    fn space_actions(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
+    /// SpacePolicy property
+    #[dbus_proxy(property)]
+    fn space_policy(&self) -> zbus::Result<String>;
+
+    /// VolumeGroups property
+    #[dbus_proxy(property)]
+    fn volume_groups(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
     /// Volumes property
     #[dbus_proxy(property)]
     fn volumes(
@@ -12,61 +12,97 @@
 //! taking care of the conversions automatically. The newtype [SettingValue] takes care of such a
 //! conversion.
 //!
+mod args;
+
+pub use args::{read_values, ArgValue, SettingArg, SettingArgError, SettingOperator, ValueSource};
+
+use crate::install_settings::Scope;
+use serde::Serialize;
 use std::collections::HashMap;
-/// For plain structs, the implementation can be derived.
-///
-/// TODO: derive for top-level structs too
 use std::convert::TryFrom;
+use std::str::FromStr;
+use thiserror::Error;
 
 /// Implements support for easily settings attributes values given an ID (`"users.name"`) and a
 /// string value (`"Foo bar"`).
 ///
-/// In the example below, the trait is manually implemented for `InstallSettings` and derived for
-/// `UserSettings`.
+/// The implementation can be derived, even for structs which contain other sections. In the
+/// example below, `user` is a nested section of `InstallSettings`.
 ///
 /// ```
 /// # use agama_derive::Settings;
-/// # use agama_lib::settings::{Settings, SettingValue};
+/// # use agama_lib::settings::{
+/// #     MergeStrategy, Settings, SettingDescription, SettingObject, SettingValue, SettingsError,
+/// # };
 ///
-/// #[derive(Settings)]
+/// #[derive(Default, Settings)]
 /// struct UserSettings {
 ///   name: Option<String>,
 ///   enabled: Option<bool>
 /// }
 ///
+/// #[derive(Default, Settings)]
 /// struct InstallSettings {
-///   user: UserSettings
+///   #[settings(nested)]
+///   user: Option<UserSettings>
 /// }
 ///
-/// impl Settings for InstallSettings {
-///   fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), &'static str> {
-///     if let Some((ns, id)) = attr.split_once('.') {
-///       match ns {
-///         "user" => self.user.set(id, value)?,
-///         _ => return Err("unknown attribute")
-///       }
-///     }
-///     Ok(())
-///   }
-/// }
-///
-/// let user = UserSettings { name: Some(String::from("foo")), enabled: Some(false) };
-/// let mut settings = InstallSettings { user };
-/// settings.set("user.name", SettingValue("foo.bar".to_string()));
-/// settings.set("user.enabled", SettingValue("true".to_string()));
-/// assert!(&settings.user.enabled.unwrap());
-/// assert_eq!(&settings.user.name.unwrap(), "foo.bar");
+/// let mut settings = InstallSettings::default();
+/// settings.set("user.name", SettingValue("foo.bar".to_string())).unwrap();
+/// settings.set("user.enabled", SettingValue("true".to_string())).unwrap();
+/// let user = settings.user.unwrap();
+/// assert!(user.enabled.unwrap());
+/// assert_eq!(user.name.unwrap(), "foo.bar");
 /// ```
 pub trait Settings {
-    fn add(&mut self, _attr: &str, _value: SettingObject) -> Result<(), &'static str> {
-        Err("unknown collection")
+    /// Describes the known settings
+    fn settings() -> Vec<SettingDescription>
+    where
+        Self: Sized,
+    {
+        vec![]
+    }
+
+    fn add(&mut self, attr: &str, _value: SettingObject) -> Result<(), SettingsError> {
+        Err(SettingsError::UnknownCollection(attr.to_string()))
+    }
+
+    /// Returns the value of an attribute
+    ///
+    /// Unset attributes are represented as `null`.
+    fn get(&self, attr: &str) -> Result<serde_json::Value, SettingsError> {
+        Err(SettingsError::UnknownKey(attr.to_string()))
+    }
+
+    fn set(&mut self, attr: &str, _value: SettingValue) -> Result<(), SettingsError> {
+        Err(SettingsError::UnknownKey(attr.to_string()))
     }
 
-    fn set(&mut self, _attr: &str, _value: SettingValue) -> Result<(), &'static str> {
-        Err("unknown attribute")
+    /// Removes an element from a collection
+    ///
+    /// The element is removed if all the given values match.
+    fn remove(&mut self, attr: &str, _value: SettingObject) -> Result<(), SettingsError> {
+        Err(SettingsError::UnknownCollection(attr.to_string()))
     }
 
-    fn merge(&mut self, _other: &Self)
+    /// Unsets an attribute, clearing it in the case of a collection
+    fn unset(&mut self, attr: &str) -> Result<(), SettingsError> {
+        Err(SettingsError::UnknownKey(attr.to_string()))
+    }
+
+    /// Merges the settings from `other`, using the default strategy of each collection
+    fn merge(&mut self, other: &Self)
+    where
+        Self: Sized,
+    {
+        self.merge_with(other, None)
+    }
+
+    /// Merges the settings from `other`
+    ///
+    /// The defined values of `other` replace the current ones. The collections are merged
+    /// according to `strategy` or, if it is `None`, to their own default strategy.
+    fn merge_with(&mut self, _other: &Self, _strategy: Option<MergeStrategy>)
     where
         Self: Sized,
     {
@@ -74,6 +110,133 @@ pub trait Settings {
     }
 }
 
+/// How to merge two collections (e.g., the storage devices)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MergeStrategy {
+    /// Replace the elements of the collection, if the new one is not empty
+    #[default]
+    Replace,
+    /// Add the new elements at the end of the collection
+    Append,
+    /// Replace the elements with the same key (e.g., the device name) and add the rest
+    MergeByKey,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "replace" => Ok(Self::Replace),
+            "append" => Ok(Self::Append),
+            "merge-by-key" => Ok(Self::MergeByKey),
+            _ => Err("Unknown merge strategy"),
+        }
+    }
+}
+
+/// Describes a setting
+///
+/// The [Settings] derive macro registers every field, using its doc comment as description.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingDescription {
+    /// Key used in the profiles and in the command line (e.g., `"user.userName"`)
+    pub key: String,
+    /// Attribute name as expected by [Settings] (e.g., `"user.user_name"`)
+    #[serde(skip)]
+    pub attr: String,
+    /// Alternative keys
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Type of the value (`"string"`, `"boolean"`, `"list"`, `"integer"` or `"object"`)
+    pub value_type: String,
+    /// Description of the setting
+    pub description: String,
+    /// Whether it is a collection, whose elements are handled with `add` and `remove`
+    pub collection: bool,
+    /// Whether it is a secret (e.g., a password), which can be read from a prompt, a file or an
+    /// environment variable (see [ValueSource::secret_reference])
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    /// Fields of the elements of a collection which are secrets (e.g., `"password"`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secret_fields: Vec<String>,
+    /// Scope the setting belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Scope>,
+}
+
+impl SettingDescription {
+    /// Returns the description of the setting within the given section
+    ///
+    /// * `key`: section key (e.g., `"firstUser"`).
+    /// * `attr`: section attribute name (e.g., `"first_user"`).
+    /// * `scope`: scope of the section, if it is known.
+    pub fn in_section(self, key: &str, attr: &str, scope: Option<Scope>) -> Self {
+        Self {
+            key: format!("{key}.{}", self.key),
+            attr: format!("{attr}.{}", self.attr),
+            aliases: self.aliases.iter().map(|a| format!("{key}.{a}")).collect(),
+            ..self.with_scope(scope)
+        }
+    }
+
+    /// Returns the description of the setting with the given scope, if it is known
+    pub fn with_scope(self, scope: Option<Scope>) -> Self {
+        Self {
+            scope: scope.or(self.scope),
+            ..self
+        }
+    }
+
+    /// Whether the given key refers to this setting (in camelCase or snake_case)
+    pub fn matches(&self, key: &str) -> bool {
+        self.key == key || self.attr == key || self.aliases.iter().any(|a| a == key)
+    }
+}
+
+/// Returns the known key which is the most similar to the given one
+///
+/// It is meant to suggest the right key when there is a typo. Keys which are too different are
+/// not considered.
+///
+/// ```
+/// # use agama_lib::settings::suggest;
+/// let keys = ["user.userName", "user.fullName"];
+/// assert_eq!(suggest("user.username", keys), Some("user.userName"));
+/// assert_eq!(suggest("software.product", keys), None);
+/// ```
+pub fn suggest<'a>(key: &str, known: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let key = key.to_lowercase();
+    known
+        .into_iter()
+        .map(|k| (distance(&key, &k.to_lowercase()), k))
+        .filter(|(d, _)| *d <= 3)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+/// Levenshtein distance between two strings
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
 /// Represents a string-based value and allows converting them to other types
 ///
 /// Supporting more conversions is a matter of implementing the [std::convert::TryFrom] trait for
@@ -84,7 +247,7 @@ pub trait Settings {
 //
 ///   let value = SettingValue("true".to_string());
 ///   let value: bool = value.try_into().expect("the conversion failed");
-///   assert_eq!(value, true);
+///   assert!(value);
 /// ```
 #[derive(Clone)]
 pub struct SettingValue(pub String);
@@ -104,55 +267,229 @@ impl From<HashMap<String, String>> for SettingObject {
     }
 }
 
-impl TryFrom<SettingValue> for bool {
-    type Error = &'static str;
+/// Builds an object from pairs of field names and values
+///
+/// ```
+/// # use agama_lib::settings::SettingObject;
+/// let object = SettingObject::from([("name", "/dev/sda"), ("biggest", "true")]);
+/// assert_eq!(object.get("name").unwrap().0, "/dev/sda");
+/// ```
+impl<const N: usize> From<[(&str, &str); N]> for SettingObject {
+    fn from(pairs: [(&str, &str); N]) -> SettingObject {
+        SettingObject(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), SettingValue(v.to_string())))
+                .collect(),
+        )
+    }
+}
 
-    fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
-        match value.0.to_lowercase().as_str() {
-            "true" | "yes" | "t" => Ok(true),
-            "false" | "no" | "f" => Ok(false),
-            _ => Err("not a valid boolean"),
+impl SettingObject {
+    /// Returns the value of the given field, if any
+    pub fn get(&self, name: &str) -> Option<SettingValue> {
+        self.0.get(name).cloned()
+    }
+
+    /// Returns the value of the given field, failing if it is not set
+    ///
+    /// ```
+    /// # use agama_lib::settings::{SettingObject, SettingsError};
+    /// let object = SettingObject::from([("name", "/dev/sda")]);
+    /// assert_eq!(object.required("name").unwrap().0, "/dev/sda");
+    /// assert_eq!(object.required("size").err(), Some(SettingsError::missing_field("size")));
+    /// ```
+    pub fn required(&self, name: &str) -> Result<SettingValue, SettingsError> {
+        self.get(name)
+            .ok_or_else(|| SettingsError::missing_field(name))
+    }
+}
+
+/// Error converting a [SettingValue] to a given type
+///
+/// ```
+/// # use agama_lib::settings::{ConversionError, SettingValue};
+/// let value: Result<u32, ConversionError> = SettingValue("ten".to_string()).try_into();
+/// assert_eq!(value.unwrap_err().to_string(), "not a valid integer: 'ten'");
+/// ```
+#[derive(Debug, Error, PartialEq)]
+#[error("{reason}: '{value}'")]
+pub struct ConversionError {
+    /// Reason, including the expected type (e.g., "not a valid integer")
+    pub reason: &'static str,
+    /// Rejected value
+    pub value: String,
+}
+
+impl ConversionError {
+    pub fn new(reason: &'static str, value: &SettingValue) -> Self {
+        Self {
+            reason,
+            value: value.0.clone(),
+        }
+    }
+}
+
+/// Error handling the settings
+///
+/// The errors carry the full key of the setting (e.g., `"storage.devices"`). When the error
+/// happens within a section, the key is completed by the section using
+/// [SettingsError::in_section].
+#[derive(Debug, Error, PartialEq)]
+pub enum SettingsError {
+    #[error("Unknown setting '{0}'")]
+    UnknownKey(String),
+    #[error("Unknown collection '{0}'")]
+    UnknownCollection(String),
+    #[error("Wrong value for '{key}': {source}")]
+    WrongValue {
+        key: String,
+        #[source]
+        source: ConversionError,
+    },
+    #[error("Missing '{field}' for '{key}'")]
+    MissingField { key: String, field: String },
+    #[error("No matching element in '{0}'")]
+    ElementNotFound(String),
+    #[error("Setting '{0}' cannot be unset")]
+    CannotUnset(String),
+}
+
+impl SettingsError {
+    /// Returns an error for a value which is missing in a collection element
+    ///
+    /// The key is expected to be set later using [SettingsError::in_section].
+    pub fn missing_field(field: &str) -> Self {
+        Self::MissingField {
+            key: String::new(),
+            field: field.to_string(),
+        }
+    }
+
+    /// Prepends the name of a section to the key of the setting
+    ///
+    /// ```
+    /// # use agama_lib::settings::SettingsError;
+    /// let error = SettingsError::UnknownKey("name".to_string()).in_section("user");
+    /// assert_eq!(error.to_string(), "Unknown setting 'user.name'");
+    /// ```
+    pub fn in_section(self, section: &str) -> Self {
+        let prefix = |key: String| {
+            if key.is_empty() {
+                section.to_string()
+            } else {
+                format!("{section}.{key}")
+            }
+        };
+        match self {
+            Self::UnknownKey(key) => Self::UnknownKey(prefix(key)),
+            Self::UnknownCollection(key) => Self::UnknownCollection(prefix(key)),
+            Self::WrongValue { key, source } => Self::WrongValue {
+                key: prefix(key),
+                source,
+            },
+            Self::MissingField { key, field } => Self::MissingField {
+                key: prefix(key),
+                field,
+            },
+            Self::ElementNotFound(key) => Self::ElementNotFound(prefix(key)),
+            Self::CannotUnset(key) => Self::CannotUnset(prefix(key)),
         }
     }
 }
 
-impl TryFrom<SettingValue> for Option<bool> {
-    type Error = &'static str;
+impl From<ConversionError> for SettingsError {
+    fn from(source: ConversionError) -> Self {
+        Self::WrongValue {
+            key: String::new(),
+            source,
+        }
+    }
+}
+
+/// Implements the conversion to `Option<T>` for the given types
+macro_rules! impl_option_conversion {
+    ($($type:ty),*) => {
+        $(impl TryFrom<SettingValue> for Option<$type> {
+            type Error = ConversionError;
+
+            fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+                Ok(Some(value.try_into()?))
+            }
+        })*
+    };
+}
+
+/// Implements the conversion to the given integer types (and their `Option` counterparts)
+macro_rules! impl_integer_conversion {
+    ($($type:ty),*) => {
+        $(impl TryFrom<SettingValue> for $type {
+            type Error = ConversionError;
+
+            fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+                value
+                    .0
+                    .trim()
+                    .parse()
+                    .map_err(|_| ConversionError::new("not a valid integer", &value))
+            }
+        })*
+
+        impl_option_conversion!($($type),*);
+    };
+}
+
+impl_integer_conversion!(u8, u16, u32, u64, usize, i32, i64);
+impl_option_conversion!(bool, String, Vec<String>);
+
+impl TryFrom<SettingValue> for bool {
+    type Error = ConversionError;
 
     fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
-        Ok(Some(value.try_into()?))
+        match value.0.to_lowercase().as_str() {
+            "true" | "yes" | "t" => Ok(true),
+            "false" | "no" | "f" => Ok(false),
+            _ => Err(ConversionError::new("not a valid boolean", &value)),
+        }
     }
 }
 
 impl TryFrom<SettingValue> for String {
-    type Error = &'static str;
+    type Error = ConversionError;
 
     fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
         Ok(value.0)
     }
 }
 
-impl TryFrom<SettingValue> for Option<String> {
-    type Error = &'static str;
+/// Converts a comma-separated list (e.g., "vim,git") into a vector
+impl TryFrom<SettingValue> for Vec<String> {
+    type Error = ConversionError;
 
     fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
-        Ok(Some(value.try_into()?))
+        Ok(value
+            .0
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use agama_derive::SettingEnum;
 
     #[test]
     fn test_try_from_bool() {
         let value = SettingValue("true".to_string());
         let value: bool = value.try_into().unwrap();
-        assert_eq!(value, true);
+        assert!(value);
 
         let value = SettingValue("false".to_string());
         let value: bool = value.try_into().unwrap();
-        assert_eq!(value, false);
+        assert!(!value);
     }
 
     #[test]
@@ -161,4 +498,49 @@ mod tests {
         let value: String = value.try_into().unwrap();
         assert_eq!(value, "some value");
     }
+
+    #[test]
+    fn test_try_from_integer() {
+        let value: u64 = SettingValue(" 1024 ".to_string()).try_into().unwrap();
+        assert_eq!(value, 1024);
+
+        let value: Result<Option<u8>, _> = SettingValue("256".to_string()).try_into();
+        assert_eq!(
+            value.unwrap_err(),
+            ConversionError {
+                reason: "not a valid integer",
+                value: "256".to_string()
+            }
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, SettingEnum)]
+    enum Filesystem {
+        Btrfs,
+        #[serde(rename = "ext4")]
+        Ext4,
+        XfsV5,
+    }
+
+    #[test]
+    fn test_try_from_enum() {
+        let value: Filesystem = SettingValue("BTRFS".to_string()).try_into().unwrap();
+        assert_eq!(value, Filesystem::Btrfs);
+        let value: Option<Filesystem> = SettingValue("xfs_v5".to_string()).try_into().unwrap();
+        assert_eq!(value, Some(Filesystem::XfsV5));
+        assert_eq!(Filesystem::XfsV5.to_string(), "xfs_v5");
+
+        let value: Result<Filesystem, _> = SettingValue("ext3".to_string()).try_into();
+        assert_eq!(
+            value.unwrap_err().to_string(),
+            "expected one of btrfs, ext4, xfs_v5: 'ext3'"
+        );
+    }
+
+    #[test]
+    fn test_try_from_list() {
+        let value = SettingValue("vim, git,".to_string());
+        let value: Vec<String> = value.try_into().unwrap();
+        assert_eq!(value, vec!["vim".to_string(), "git".to_string()]);
+    }
 }
@@ -0,0 +1,458 @@
+//! Command-line arguments to change the settings
+//!
+//! The arguments look like `key=value` and support a few operators:
+//!
+//! * `key=value`: sets the value as it is (e.g., `user.fullName=Jane Doe`).
+//! * `key:=<json>`: sets a JSON value (e.g., `software.patterns:=["base","gnome"]`). It is the
+//!   only way to replace a whole collection (e.g., `storage.devices:=[{"name":"/dev/sda"}]`) and
+//!   `null` unsets the setting.
+//! * `key+=item`: adds an item to a list (e.g., `software.patterns+=gnome`) or an element,
+//!   written in JSON, to a collection.
+//!
+//! Values starting with `@` are read from somewhere else:
+//!
+//! * `@path` or `@file:path`: from a file (e.g., `root.sshKey=@id_rsa.pub`).
+//! * `@-`: from the standard input.
+//! * `@env:VAR`: from an environment variable.
+//! * `@prompt`: asking the user, which is meant for secrets (e.g., `root.password=@prompt`).
+//!
+//! Use `\@` for values which start with a literal `@`.
+use super::{ConversionError, SettingObject, SettingValue, SettingsError};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::{env, fs};
+use thiserror::Error;
+
+/// Operator of a command-line argument
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SettingOperator {
+    /// `key=value`
+    Set,
+    /// `key:=<json>`
+    SetJson,
+    /// `key+=item`
+    Append,
+}
+
+/// Where to read the value of an argument from
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueSource {
+    /// The value is given in the argument
+    Text(String),
+    /// `@path` or `@file:path`
+    File(PathBuf),
+    /// `@-`
+    Stdin,
+    /// `@env:VAR`
+    Env(String),
+    /// `@prompt`
+    Prompt,
+}
+
+impl ValueSource {
+    /// Parses a reference to a secret (`@prompt`, `@file:path` or `@env:VAR`)
+    ///
+    /// Unlike the command-line arguments, these are the only references supported in the
+    /// profiles, so a password like `"@home"` is not mistaken for a file.
+    ///
+    /// ```
+    /// # use agama_lib::settings::ValueSource;
+    /// let source = ValueSource::secret_reference("@env:ROOT_PASSWORD");
+    /// assert_eq!(source, Some(ValueSource::Env("ROOT_PASSWORD".to_string())));
+    /// assert_eq!(ValueSource::secret_reference("@home"), None);
+    /// ```
+    pub fn secret_reference(value: &str) -> Option<Self> {
+        if value == "@prompt" {
+            return Some(Self::Prompt);
+        }
+        if let Some(path) = value.strip_prefix("@file:").filter(|p| !p.is_empty()) {
+            return Some(Self::File(PathBuf::from(path)));
+        }
+        value
+            .strip_prefix("@env:")
+            .filter(|v| !v.is_empty())
+            .map(|v| Self::Env(v.to_string()))
+    }
+
+    /// Reads the value for the given setting
+    ///
+    /// * `stdin`: standard input.
+    /// * `prompt`: function to ask the user for the value of a setting.
+    ///
+    /// A single trailing newline is removed from the contents of files and the standard input.
+    pub fn read(
+        &self,
+        key: &str,
+        mut stdin: impl Read,
+        prompt: impl FnOnce(&str) -> io::Result<String>,
+    ) -> Result<String, SettingArgError> {
+        match self {
+            Self::Text(text) => Ok(text.clone()),
+            Self::File(path) => {
+                let contents = fs::read_to_string(path)
+                    .map_err(|e| SettingArgError::Read(path.display().to_string(), e))?;
+                Ok(trim_newline(contents))
+            }
+            Self::Stdin => {
+                let mut contents = String::new();
+                stdin
+                    .read_to_string(&mut contents)
+                    .map_err(|e| SettingArgError::Read("-".to_string(), e))?;
+                Ok(trim_newline(contents))
+            }
+            Self::Env(name) => {
+                env::var(name).map_err(|_| SettingArgError::UndefinedVariable(name.clone()))
+            }
+            Self::Prompt => prompt(key).map_err(|e| SettingArgError::Prompt(key.to_string(), e)),
+        }
+    }
+}
+
+/// Command-line argument to change a setting
+///
+/// ```
+/// # use agama_lib::settings::{SettingArg, SettingOperator, ValueSource};
+/// let arg: SettingArg = "software.patterns+=gnome".parse().unwrap();
+/// assert_eq!(arg.key, "software.patterns");
+/// assert_eq!(arg.operator, SettingOperator::Append);
+/// assert_eq!(arg.source, ValueSource::Text("gnome".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingArg {
+    pub key: String,
+    pub operator: SettingOperator,
+    pub source: ValueSource,
+}
+
+/// Error parsing or reading a command-line argument
+#[derive(Debug, Error)]
+pub enum SettingArgError {
+    #[error("expected key=value, key:=<json> or key+=item")]
+    MissingOperator,
+    #[error("'{0}' is not a valid key")]
+    InvalidKey(String),
+    #[error("missing the file name after '@'")]
+    MissingFileName,
+    #[error("not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("could not read '{0}': {1}")]
+    Read(String, #[source] io::Error),
+    #[error("the standard input can be read only once")]
+    StdinReused,
+    #[error("the environment variable '{0}' is not defined")]
+    UndefinedVariable(String),
+    #[error("could not ask for '{0}': {1}")]
+    Prompt(String, #[source] io::Error),
+    #[error(transparent)]
+    Settings(#[from] SettingsError),
+}
+
+impl FromStr for SettingArg {
+    type Err = SettingArgError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (left, value) = s.split_once('=').ok_or(SettingArgError::MissingOperator)?;
+        let (key, operator) = if let Some(key) = left.strip_suffix(':') {
+            (key, SettingOperator::SetJson)
+        } else if let Some(key) = left.strip_suffix('+') {
+            (key, SettingOperator::Append)
+        } else {
+            (left, SettingOperator::Set)
+        };
+
+        let valid_key = !key.is_empty()
+            && key.split('.').all(|part| {
+                !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        if !valid_key {
+            return Err(SettingArgError::InvalidKey(key.to_string()));
+        }
+
+        let source = if let Some(source) = ValueSource::secret_reference(value) {
+            source
+        } else if value == "@-" {
+            ValueSource::Stdin
+        } else if let Some(path) = value.strip_prefix('@') {
+            if path.is_empty() {
+                return Err(SettingArgError::MissingFileName);
+            }
+            ValueSource::File(PathBuf::from(path))
+        } else if let Some(value) = value.strip_prefix("\\@") {
+            ValueSource::Text(format!("@{value}"))
+        } else {
+            ValueSource::Text(value.to_string())
+        };
+
+        // report malformed JSON as soon as possible
+        if let (SettingOperator::SetJson, ValueSource::Text(json)) = (operator, &source) {
+            serde_json::from_str::<serde_json::Value>(json)?;
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            operator,
+            source,
+        })
+    }
+}
+
+impl SettingArg {
+    /// Returns the value of the argument, reading it from its source (see [ValueSource::read])
+    pub fn value(
+        &self,
+        stdin: impl Read,
+        prompt: impl FnOnce(&str) -> io::Result<String>,
+    ) -> Result<ArgValue, SettingArgError> {
+        let text = self.source.read(&self.key, stdin, prompt)?;
+
+        match self.operator {
+            SettingOperator::SetJson => Ok(ArgValue::Json(serde_json::from_str(&text)?)),
+            SettingOperator::Set | SettingOperator::Append => Ok(ArgValue::Text(text)),
+        }
+    }
+}
+
+/// Returns the values of the given arguments, reading the standard input at most once
+///
+/// The `prompt` function is called for the arguments whose value is `@prompt`.
+pub fn read_values(
+    args: &[SettingArg],
+    mut prompt: impl FnMut(&str) -> io::Result<String>,
+) -> Result<Vec<ArgValue>, SettingArgError> {
+    let from_stdin = args
+        .iter()
+        .filter(|a| a.source == ValueSource::Stdin)
+        .count();
+    if from_stdin > 1 {
+        return Err(SettingArgError::StdinReused);
+    }
+    args.iter()
+        .map(|arg| arg.value(io::stdin(), &mut prompt))
+        .collect()
+}
+
+fn trim_newline(mut text: String) -> String {
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    text
+}
+
+/// Value of a command-line argument
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgValue {
+    Text(String),
+    Json(serde_json::Value),
+}
+
+impl ArgValue {
+    /// Converts the value into a [SettingValue]
+    ///
+    /// JSON strings, numbers and booleans are converted to text and lists of them are joined with
+    /// commas. Hence, the items of a list cannot contain commas.
+    ///
+    /// ```
+    /// # use agama_lib::settings::ArgValue;
+    /// let value = ArgValue::Json(serde_json::json!(["base", "gnome"]));
+    /// assert_eq!(value.to_setting_value().unwrap().0, "base,gnome");
+    /// ```
+    pub fn to_setting_value(&self) -> Result<SettingValue, ConversionError> {
+        let json = match self {
+            Self::Text(text) => return Ok(SettingValue(text.clone())),
+            Self::Json(json) => json,
+        };
+
+        match json {
+            serde_json::Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| match scalar_text(item) {
+                        Some(text) if !text.contains(',') => Ok(text),
+                        _ => Err(json_error("not a valid list item", item)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(SettingValue(items.join(",")))
+            }
+            json => scalar_text(json).map(SettingValue).ok_or_else(|| {
+                json_error("expected a string, a number, a boolean or a list", json)
+            }),
+        }
+    }
+
+    /// Converts the value into a [SettingObject]
+    ///
+    /// The text values are parsed as JSON. The lists in the object (e.g., the devices of a RAID)
+    /// are converted as in [ArgValue::to_setting_value].
+    pub fn to_setting_object(&self) -> Result<SettingObject, ConversionError> {
+        let json = self.to_json()?;
+        let serde_json::Value::Object(fields) = json else {
+            return Err(json_error("expected a JSON object", &json));
+        };
+
+        let mut object = HashMap::new();
+        for (name, value) in fields {
+            object.insert(name, ArgValue::Json(value).to_setting_value()?);
+        }
+        Ok(SettingObject(object))
+    }
+
+    /// Converts the value into a list of [SettingObject]
+    ///
+    /// It accepts a list of JSON objects or a single one.
+    pub fn to_setting_objects(&self) -> Result<Vec<SettingObject>, ConversionError> {
+        match self.to_json()? {
+            serde_json::Value::Array(items) => items
+                .into_iter()
+                .map(|item| ArgValue::Json(item).to_setting_object())
+                .collect(),
+            json => Ok(vec![ArgValue::Json(json).to_setting_object()?]),
+        }
+    }
+
+    fn to_json(&self) -> Result<serde_json::Value, ConversionError> {
+        match self {
+            Self::Text(text) => serde_json::from_str(text).map_err(|_| ConversionError {
+                reason: "not valid JSON",
+                value: text.clone(),
+            }),
+            Self::Json(json) => Ok(json.clone()),
+        }
+    }
+}
+
+/// Returns the text of a JSON string, number or boolean
+fn scalar_text(json: &serde_json::Value) -> Option<String> {
+    match json {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Some(json.to_string()),
+        _ => None,
+    }
+}
+
+fn json_error(reason: &'static str, json: &serde_json::Value) -> ConversionError {
+    ConversionError {
+        reason,
+        value: json.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let arg: SettingArg = "user.fullName=Jane = Doe".parse().unwrap();
+        assert_eq!(arg.key, "user.fullName");
+        assert_eq!(arg.operator, SettingOperator::Set);
+        assert_eq!(arg.source, ValueSource::Text("Jane = Doe".to_string()));
+
+        let arg: SettingArg = "root.sshKey=@/root/.ssh/id_rsa.pub".parse().unwrap();
+        assert_eq!(
+            arg.source,
+            ValueSource::File("/root/.ssh/id_rsa.pub".into())
+        );
+
+        let arg: SettingArg = "user.password=\\@secret".parse().unwrap();
+        assert_eq!(arg.source, ValueSource::Text("@secret".to_string()));
+
+        let arg: SettingArg = "root.password=@env:ROOT_PASSWORD".parse().unwrap();
+        assert_eq!(arg.source, ValueSource::Env("ROOT_PASSWORD".to_string()));
+
+        let arg: SettingArg = "root.password=@file:/run/password".parse().unwrap();
+        assert_eq!(arg.source, ValueSource::File("/run/password".into()));
+
+        let arg: SettingArg = "storage.devices:=@-".parse().unwrap();
+        assert_eq!(arg.operator, SettingOperator::SetJson);
+        assert_eq!(arg.source, ValueSource::Stdin);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |s: &str| s.parse::<SettingArg>().unwrap_err();
+        assert!(matches!(
+            error("user.fullName"),
+            SettingArgError::MissingOperator
+        ));
+        assert!(matches!(error("=jane"), SettingArgError::InvalidKey(_)));
+        assert!(matches!(
+            error("user..name=jane"),
+            SettingArgError::InvalidKey(_)
+        ));
+        assert!(matches!(
+            error("user.name=@"),
+            SettingArgError::MissingFileName
+        ));
+        assert!(matches!(
+            error("software.patterns:=[base"),
+            SettingArgError::InvalidJson(_)
+        ));
+    }
+
+    fn no_prompt(_key: &str) -> io::Result<String> {
+        Err(io::Error::other("not a terminal"))
+    }
+
+    #[test]
+    fn test_value() {
+        let arg: SettingArg = "storage.lvm:=@-".parse().unwrap();
+        let value = arg.value("true\n".as_bytes(), no_prompt).unwrap();
+        assert_eq!(value, ArgValue::Json(json!(true)));
+
+        let arg: SettingArg = "user.fullName=@-".parse().unwrap();
+        let value = arg.value("Jane Doe\n".as_bytes(), no_prompt).unwrap();
+        assert_eq!(value, ArgValue::Text("Jane Doe".to_string()));
+
+        let arg: SettingArg = "root.password=@prompt".parse().unwrap();
+        let value = arg.value(io::empty(), |key| Ok(format!("{key}!"))).unwrap();
+        assert_eq!(value, ArgValue::Text("root.password!".to_string()));
+        assert!(matches!(
+            arg.value(io::empty(), no_prompt),
+            Err(SettingArgError::Prompt(..))
+        ));
+
+        let arg: SettingArg = "root.password=@env:AGAMA_UNDEFINED_VARIABLE"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            arg.value(io::empty(), no_prompt),
+            Err(SettingArgError::UndefinedVariable(_))
+        ));
+
+        let arg: SettingArg = "user.fullName=@-".parse().unwrap();
+        let args = vec![arg.clone(), arg];
+        assert!(matches!(
+            read_values(&args, no_prompt),
+            Err(SettingArgError::StdinReused)
+        ));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let value = ArgValue::Json(json!(1024));
+        assert_eq!(value.to_setting_value().unwrap().0, "1024");
+
+        let value = ArgValue::Json(json!(["base", "a,b"]));
+        assert!(value.to_setting_value().is_err());
+
+        let value = ArgValue::Text("{\"name\": \"/dev/sda\"}".to_string());
+        let object = value.to_setting_object().unwrap();
+        assert_eq!(object.0.get("name").unwrap().0, "/dev/sda");
+
+        let value = ArgValue::Json(json!([{ "name": "/dev/sda" }, { "name": "/dev/sdb" }]));
+        assert_eq!(value.to_setting_objects().unwrap().len(), 2);
+
+        let value = ArgValue::Json(json!({ "devices": ["/dev/sda", "/dev/sdb"] }));
+        let object = value.to_setting_object().unwrap();
+        assert_eq!(object.0.get("devices").unwrap().0, "/dev/sda,/dev/sdb");
+
+        let value = ArgValue::Json(json!({ "name": { "path": "/dev/sda" } }));
+        assert!(value.to_setting_object().is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::proxies::Software1Proxy;
+use super::proxies::{Software1Proxy, SoftwareProposalProxy};
 use crate::error::ServiceError;
 use serde::Serialize;
 use zbus::Connection;
@@ -14,15 +14,30 @@ pub struct Product {
     pub description: String,
 }
 
+/// Kind of resolvable to select for installation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResolvableType {
+    Package = 0,
+    Pattern = 1,
+}
+
+/// ID used to register the resolvables selected through this client
+///
+/// The software service keeps a separate list of resolvables for each ID, so the ones selected by
+/// other modules are not affected.
+const RESOLVABLES_ID: &str = "agama-cli";
+
 /// D-Bus client for the software service
 pub struct SoftwareClient<'a> {
     software_proxy: Software1Proxy<'a>,
+    proposal_proxy: SoftwareProposalProxy<'a>,
 }
 
 impl<'a> SoftwareClient<'a> {
     pub async fn new(connection: Connection) -> Result<SoftwareClient<'a>, ServiceError> {
         Ok(Self {
             software_proxy: Software1Proxy::new(&connection).await?,
+            proposal_proxy: SoftwareProposalProxy::new(&connection).await?,
         })
     }
 
@@ -57,4 +72,28 @@ impl<'a> SoftwareClient<'a> {
     pub async fn select_product(&self, product_id: &str) -> Result<(), ServiceError> {
         Ok(self.software_proxy.select_product(product_id).await?)
     }
+
+    /// Returns the resolvables of the given type selected through this client
+    pub async fn selected_resolvables(
+        &self,
+        kind: ResolvableType,
+    ) -> Result<Vec<String>, ServiceError> {
+        Ok(self
+            .proposal_proxy
+            .get_resolvables(RESOLVABLES_ID, kind as u8, false)
+            .await?)
+    }
+
+    /// Selects the resolvables of the given type to install, replacing the previous selection
+    pub async fn select_resolvables(
+        &self,
+        kind: ResolvableType,
+        names: &[String],
+    ) -> Result<(), ServiceError> {
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        Ok(self
+            .proposal_proxy
+            .set_resolvables(RESOLVABLES_ID, kind as u8, &names, false)
+            .await?)
+    }
 }
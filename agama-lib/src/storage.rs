@@ -1,14 +1,175 @@
+mod device;
+mod iscsi;
+mod layout;
+mod selector;
+mod size;
+
+pub use device::StorageDevice;
+pub use iscsi::{IscsiAuth, IscsiClient, IscsiNode, IscsiStartup, DEFAULT_ISCSI_PORT};
+pub use layout::{validate_layout, LayoutError, LogicalVolume, Raid, RaidLevel, VolumeGroup};
+pub use selector::{DeviceSelector, ParseSizeConstraintError, SelectorError, SizeConstraint};
+pub use size::{DiskSize, ParseDiskSizeError};
+
 use super::proxies::{CalculatorProxy, Storage1Proxy, StorageProposalProxy};
 use crate::error::ServiceError;
-use serde::Serialize;
+use crate::install_settings::ExpertStorageSettings;
+use crate::settings::{ConversionError, SettingValue};
+use agama_derive::SettingEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use zbus::zvariant::OwnedValue;
 use zbus::Connection;
 
-/// Represents a storage device
-#[derive(Serialize, Debug)]
-pub struct StorageDevice {
-    name: String,
-    description: String,
+/// Encryption method for the storage devices
+///
+/// `TpmFde` stands for full disk encryption unlocked by the TPM2 chip.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionMethod {
+    Luks1,
+    Luks2,
+    TpmFde,
+}
+
+/// Password-based key derivation function for LUKS2
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PbkdFunction {
+    Pbkdf2,
+    Argon2i,
+    Argon2id,
+}
+
+/// Policy to make space for the new system
+///
+/// `Delete` removes all the existing partitions, `Resize` shrinks them as needed, `Keep` only
+/// uses the free space and `Custom` applies the given actions to each partition.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SpacePolicy {
+    Delete,
+    Resize,
+    Keep,
+    Custom,
+}
+
+/// Action to perform on an existing partition when using the custom space policy
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionAction {
+    Delete,
+    Resize,
+    Keep,
+}
+
+/// Settings to calculate a storage proposal
+#[derive(Clone, Debug, Default)]
+pub struct ProposalSettings {
+    pub candidate_devices: Vec<String>,
+    /// Device to install the bootloader to; the service chooses one if it is not set
+    pub boot_device: Option<String>,
+    pub lvm: bool,
+    pub encryption_password: String,
+    /// Encryption method; the service uses its default one if it is not set
+    pub encryption_method: Option<EncryptionMethod>,
+    /// Key derivation function; the service uses its default one if it is not set
+    pub encryption_pbkdf: Option<PbkdFunction>,
+    /// Space policy; the service uses its default one if it is not set
+    pub space_policy: Option<SpacePolicy>,
+    /// Actions for the custom space policy, as pairs of partition name and action
+    pub space_actions: Vec<(String, PartitionAction)>,
+    /// Btrfs settings of the root file system
+    pub btrfs: BtrfsVolume,
+    /// Expert layout (RAIDs, volume groups and logical volumes)
+    pub layout: ExpertStorageSettings,
+}
+
+/// Btrfs settings of the root file system
+///
+/// They are sent to the service as the settings of the volume mounted at `/`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BtrfsVolume {
+    /// Whether to enable the snapshots; the service decides if it is not set
+    pub snapshots: Option<bool>,
+    /// Subvolumes, as pairs of path and whether copy-on-write is enabled
+    pub subvolumes: Vec<(String, bool)>,
+}
+
+impl BtrfsVolume {
+    const ROOT: &'static str = "/";
+
+    /// Whether there is nothing to send to the service
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Builds the settings of the root volume for the D-Bus service
+    fn to_dbus(&self) -> HashMap<&str, zbus::zvariant::Value<'_>> {
+        let mut volume = HashMap::from([("MountPath", zbus::zvariant::Value::new(Self::ROOT))]);
+        if let Some(snapshots) = self.snapshots {
+            volume.insert("Snapshots", zbus::zvariant::Value::new(snapshots));
+        }
+        if !self.subvolumes.is_empty() {
+            let subvolumes: Vec<HashMap<&str, zbus::zvariant::Value<'_>>> = self
+                .subvolumes
+                .iter()
+                .map(|(path, cow)| {
+                    HashMap::from([
+                        ("Path", zbus::zvariant::Value::new(path.as_str())),
+                        ("CopyOnWrite", zbus::zvariant::Value::new(*cow)),
+                    ])
+                })
+                .collect();
+            volume.insert("Subvolumes", zbus::zvariant::Value::new(subvolumes));
+        }
+        volume
+    }
+
+    /// Finds the root volume in the volumes of the D-Bus service and reads its settings
+    fn from_dbus(volumes: &[HashMap<String, OwnedValue>]) -> Option<Self> {
+        let root = volumes
+            .iter()
+            .find(|v| dict_value::<String>(v, "MountPath").as_deref() == Some(Self::ROOT))?;
+        let subvolumes: Vec<HashMap<String, OwnedValue>> =
+            dict_value(root, "Subvolumes").unwrap_or_default();
+        Some(Self {
+            snapshots: dict_value(root, "Snapshots"),
+            subvolumes: subvolumes
+                .iter()
+                .filter_map(|s| {
+                    let path = dict_value(s, "Path")?;
+                    Some((path, dict_value(s, "CopyOnWrite").unwrap_or(true)))
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Action to perform in the storage devices when installing the system
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Action {
+    /// Description of the action (e.g., "Delete partition /dev/sda1 (20 GiB)")
+    pub text: String,
+    /// Whether it affects a Btrfs subvolume
+    pub subvol: bool,
+    /// Whether it deletes a device, destroying its data
+    pub delete: bool,
+}
+
+impl From<HashMap<String, OwnedValue>> for Action {
+    fn from(action: HashMap<String, OwnedValue>) -> Self {
+        Self {
+            text: dict_value(&action, "Text").unwrap_or_default(),
+            subvol: dict_value(&action, "Subvol").unwrap_or_default(),
+            delete: dict_value(&action, "Delete").unwrap_or_default(),
+        }
+    }
+}
+
+/// Returns the value of a D-Bus dictionary entry if it exists and it has the expected type
+fn dict_value<T: TryFrom<OwnedValue>>(dict: &HashMap<String, OwnedValue>, key: &str) -> Option<T> {
+    dict.get(key).and_then(|v| T::try_from(v.clone()).ok())
 }
 
 /// D-Bus client for the storage service
@@ -44,7 +205,7 @@ impl<'a> StorageClient<'a> {
             .available_devices()
             .await?
             .into_iter()
-            .map(|(name, description, _)| StorageDevice { name, description })
+            .map(|(name, description, data)| StorageDevice::from_dbus(name, description, &data))
             .collect();
         Ok(devices)
     }
@@ -54,27 +215,193 @@ impl<'a> StorageClient<'a> {
         Ok(self.proposal_proxy().await?.candidate_devices().await?)
     }
 
+    /// Returns the device to install the bootloader to, if any
+    pub async fn boot_device(&self) -> Result<Option<String>, ServiceError> {
+        let device = self.proposal_proxy().await?.boot_device().await?;
+        Ok(Some(device).filter(|d| !d.is_empty()))
+    }
+
+    /// Returns whether the proposal uses LVM
+    pub async fn lvm(&self) -> Result<bool, ServiceError> {
+        Ok(self.proposal_proxy().await?.lvm().await?)
+    }
+
+    /// Returns the encryption password of the proposal
+    pub async fn encryption_password(&self) -> Result<String, ServiceError> {
+        Ok(self.proposal_proxy().await?.encryption_password().await?)
+    }
+
+    /// Returns the encryption method of the proposal, if it is known
+    pub async fn encryption_method(&self) -> Result<Option<EncryptionMethod>, ServiceError> {
+        let method = self.proposal_proxy().await?.encryption_method().await?;
+        Ok(SettingValue(method).try_into().ok())
+    }
+
+    /// Returns the key derivation function of the proposal, if it is known
+    pub async fn encryption_pbkdf(&self) -> Result<Option<PbkdFunction>, ServiceError> {
+        let pbkdf = self
+            .proposal_proxy()
+            .await?
+            .encryption_pbkd_function()
+            .await?;
+        Ok(SettingValue(pbkdf).try_into().ok())
+    }
+
+    /// Returns the space policy of the proposal, if it is known
+    pub async fn space_policy(&self) -> Result<Option<SpacePolicy>, ServiceError> {
+        let policy = self.proposal_proxy().await?.space_policy().await?;
+        Ok(SettingValue(policy).try_into().ok())
+    }
+
+    /// Returns the actions of the custom space policy, as pairs of partition name and action
+    ///
+    /// The actions which are not known are ignored.
+    pub async fn space_actions(&self) -> Result<Vec<(String, PartitionAction)>, ServiceError> {
+        let actions = self
+            .proposal_proxy()
+            .await?
+            .space_actions()
+            .await?
+            .into_iter()
+            .filter_map(|action| {
+                let device: String = dict_value(&action, "Device")?;
+                let name: String = dict_value(&action, "Action")?;
+                Some((device, SettingValue(name).try_into().ok()?))
+            })
+            .collect();
+        Ok(actions)
+    }
+
+    /// Returns the Btrfs settings of the root file system, if it is known
+    pub async fn btrfs(&self) -> Result<Option<BtrfsVolume>, ServiceError> {
+        let volumes = self.proposal_proxy().await?.volumes().await?;
+        Ok(BtrfsVolume::from_dbus(&volumes))
+    }
+
+    /// Returns the expert layout of the proposal
+    pub async fn layout(&self) -> Result<ExpertStorageSettings, ServiceError> {
+        let proxy = self.proposal_proxy().await?;
+        Ok(layout::layout_from_dbus(
+            &proxy.raids().await?,
+            &proxy.volume_groups().await?,
+            &proxy.logical_volumes().await?,
+        ))
+    }
+
+    /// Returns the actions to perform in the storage devices
+    pub async fn actions(&self) -> Result<Vec<Action>, ServiceError> {
+        let actions = self.proposal_proxy().await?.actions().await?;
+        Ok(actions.into_iter().map(Action::from).collect())
+    }
+
     /// Runs the probing process
     pub async fn probe(&self) -> Result<(), ServiceError> {
         Ok(self.storage_proxy.probe().await?)
     }
 
-    pub async fn calculate(
-        &self,
-        candidate_devices: Vec<String>,
-        encryption_password: String,
-        lvm: bool,
-    ) -> Result<u32, ServiceError> {
+    pub async fn calculate(&self, proposal: ProposalSettings) -> Result<u32, ServiceError> {
         let mut settings: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
         settings.insert(
             "CandidateDevices",
-            zbus::zvariant::Value::new(candidate_devices),
+            zbus::zvariant::Value::new(proposal.candidate_devices),
         );
         settings.insert(
             "EncryptionPassword",
-            zbus::zvariant::Value::new(encryption_password),
+            zbus::zvariant::Value::new(proposal.encryption_password),
         );
-        settings.insert("LVM", zbus::zvariant::Value::new(lvm));
+        settings.insert("LVM", zbus::zvariant::Value::new(proposal.lvm));
+        if let Some(device) = proposal.boot_device {
+            settings.insert("BootDevice", zbus::zvariant::Value::new(device));
+        }
+        if let Some(method) = proposal.encryption_method {
+            settings.insert(
+                "EncryptionMethod",
+                zbus::zvariant::Value::new(method.to_string()),
+            );
+        }
+        if let Some(pbkdf) = proposal.encryption_pbkdf {
+            settings.insert(
+                "EncryptionPBKDFunction",
+                zbus::zvariant::Value::new(pbkdf.to_string()),
+            );
+        }
+        if let Some(policy) = proposal.space_policy {
+            settings.insert(
+                "SpacePolicy",
+                zbus::zvariant::Value::new(policy.to_string()),
+            );
+        }
+        if !proposal.space_actions.is_empty() {
+            let actions: Vec<HashMap<&str, zbus::zvariant::Value<'_>>> = proposal
+                .space_actions
+                .into_iter()
+                .map(|(device, action)| {
+                    HashMap::from([
+                        ("Device", zbus::zvariant::Value::new(device)),
+                        ("Action", zbus::zvariant::Value::new(action.to_string())),
+                    ])
+                })
+                .collect();
+            settings.insert("SpaceActions", zbus::zvariant::Value::new(actions));
+        }
+        if !proposal.btrfs.is_empty() {
+            let volumes = vec![proposal.btrfs.to_dbus()];
+            settings.insert("Volumes", zbus::zvariant::Value::new(volumes));
+        }
+        if !proposal.layout.is_empty() {
+            settings.extend(layout::layout_to_dbus(&proposal.layout));
+        }
         Ok(self.calculator_proxy.calculate(settings).await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    #[test]
+    fn test_btrfs_volume_from_dbus() {
+        let subvolume = HashMap::from([
+            ("Path", Value::new("var")),
+            ("CopyOnWrite", Value::new(false)),
+        ]);
+        let volume = |path: &str| {
+            HashMap::from([
+                (
+                    "MountPath".to_string(),
+                    OwnedValue::from(Value::new(path.to_string())),
+                ),
+                ("Snapshots".to_string(), OwnedValue::from(Value::new(true))),
+                (
+                    "Subvolumes".to_string(),
+                    OwnedValue::from(Value::new(vec![subvolume.clone()])),
+                ),
+            ])
+        };
+
+        let btrfs = BtrfsVolume::from_dbus(&[volume("/home"), volume("/")]).unwrap();
+        assert_eq!(btrfs.snapshots, Some(true));
+        assert_eq!(btrfs.subvolumes, vec![("var".to_string(), false)]);
+        assert_eq!(BtrfsVolume::from_dbus(&[volume("/home")]), None);
+    }
+
+    #[test]
+    fn test_action_from_dbus() {
+        let action = HashMap::from([
+            (
+                "Text".to_string(),
+                OwnedValue::from(Value::new("Delete partition /dev/sda1")),
+            ),
+            ("Delete".to_string(), OwnedValue::from(Value::new(true))),
+        ]);
+        assert_eq!(
+            Action::from(action),
+            Action {
+                text: "Delete partition /dev/sda1".to_string(),
+                subvol: false,
+                delete: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Storage devices inventory
+use super::{dict_value, DiskSize};
+use serde::Serialize;
+use std::collections::HashMap;
+use zbus::zvariant::OwnedValue;
+
+/// Represents a storage device (a disk, a RAID, a partition, etc.)
+///
+/// The devices are built from the data of the D-Bus service, where the unknown or missing
+/// attributes are just ignored.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDevice {
+    /// Device name (e.g., "/dev/sda")
+    pub name: String,
+    /// Human readable description (e.g., "Samsung SSD 870")
+    pub description: String,
+    /// Type of device (e.g., "disk", "raid", "multipath" or "partition")
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<DiskSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Transport of the device (e.g., "nvme" or "usb")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<String>,
+    /// Type of the file system, if the device is formatted (e.g., "btrfs")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filesystem: Option<String>,
+    /// Where the file system is mounted, if it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mount_point: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<StorageDevice>,
+}
+
+impl StorageDevice {
+    /// Builds a device from the data returned by the D-Bus service
+    ///
+    /// The partitions are described by dictionaries with the same attributes, including the
+    /// `Name` and the `Description`.
+    pub(crate) fn from_dbus(
+        name: String,
+        description: String,
+        data: &HashMap<String, OwnedValue>,
+    ) -> Self {
+        let partitions: Vec<HashMap<String, OwnedValue>> =
+            dict_value(data, "Partitions").unwrap_or_default();
+        Self {
+            name,
+            description,
+            device_type: dict_value(data, "Type"),
+            size: dict_value(data, "Size").map(DiskSize::from_bytes),
+            vendor: dict_value(data, "Vendor"),
+            model: dict_value(data, "Model"),
+            serial: dict_value(data, "Serial"),
+            transport: dict_value(data, "Transport"),
+            filesystem: dict_value(data, "Filesystem"),
+            mount_point: dict_value(data, "MountPoint"),
+            partitions: partitions
+                .iter()
+                .map(|p| {
+                    let name = dict_value(p, "Name").unwrap_or_default();
+                    let description = dict_value(p, "Description").unwrap_or_default();
+                    Self::from_dbus(name, description, p)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    fn dict(entries: Vec<(&str, Value<'static>)>) -> HashMap<String, OwnedValue> {
+        entries
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), OwnedValue::from(v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_dbus() {
+        let partition = HashMap::from([
+            ("Name", Value::new("/dev/sda1")),
+            ("Size", Value::new(512_u64 * 1024 * 1024)),
+            ("Filesystem", Value::new("vfat")),
+            ("MountPoint", Value::new("/boot/efi")),
+        ]);
+        let data = dict(vec![
+            ("Type", Value::new("disk")),
+            ("Size", Value::new(500_u64 * 1024 * 1024 * 1024)),
+            ("Model", Value::new("Samsung SSD 870")),
+            ("Transport", Value::new("sata")),
+            ("Partitions", Value::new(vec![partition])),
+            ("Unknown", Value::new(true)),
+        ]);
+
+        let device = StorageDevice::from_dbus("/dev/sda".into(), "Samsung disk".into(), &data);
+        assert_eq!(device.device_type.as_deref(), Some("disk"));
+        assert_eq!(device.size.unwrap().to_string(), "500 GiB");
+        assert_eq!(device.vendor, None);
+        let partition = &device.partitions[0];
+        assert_eq!(partition.name, "/dev/sda1");
+        assert_eq!(partition.mount_point.as_deref(), Some("/boot/efi"));
+        assert_eq!(
+            serde_json::to_value(partition).unwrap(),
+            serde_json::json!({
+                "name": "/dev/sda1",
+                "description": "",
+                "size": "512 MiB",
+                "filesystem": "vfat",
+                "mountPoint": "/boot/efi"
+            })
+        );
+    }
+}
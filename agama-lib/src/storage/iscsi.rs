@@ -0,0 +1,413 @@
+//! iSCSI initiator support
+//!
+//! The storage service discovers the targets offered by a portal and exposes each of them as a
+//! node, which can be logged in and out. Once logged in, the LUNs are regular storage devices.
+use super::dict_value;
+use crate::error::ServiceError;
+use crate::proxies::{ISCSIInitiatorProxy, ISCSINodeProxy};
+use crate::settings::{ConversionError, SettingValue};
+use agama_derive::SettingEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::Connection;
+
+/// Default port of the iSCSI portals
+pub const DEFAULT_ISCSI_PORT: u32 = 3260;
+
+const NODE_INTERFACE: &str = "org.opensuse.Agama.Storage1.ISCSI.Node";
+
+/// When to log in an iSCSI node (onboot, manual or automatic)
+///
+/// `Onboot` logs in from the initrd, which is needed if the root file system is on the node.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum IscsiStartup {
+    Onboot,
+    Manual,
+    Automatic,
+}
+
+/// CHAP credentials for the discovery and the login
+///
+/// The reverse credentials are used by the target to authenticate itself to the initiator.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IscsiAuth {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub reverse_username: Option<String>,
+    pub reverse_password: Option<String>,
+}
+
+impl IscsiAuth {
+    fn to_dbus(&self) -> HashMap<&str, Value<'_>> {
+        [
+            ("Username", &self.username),
+            ("Password", &self.password),
+            ("ReverseUsername", &self.reverse_username),
+            ("ReversePassword", &self.reverse_password),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, Value::new(value.as_deref()?))))
+        .collect()
+    }
+}
+
+/// iSCSI node, as found by the discovery
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IscsiNode {
+    /// D-Bus object path of the node
+    #[serde(skip)]
+    path: String,
+    /// Target name (e.g., "iqn.2023-01.com.example:storage")
+    pub target: String,
+    /// Portal address
+    pub address: String,
+    /// Portal port
+    pub port: u32,
+    /// Network interface used to connect to the portal (e.g., "default")
+    pub interface: String,
+    /// Whether the node was configured from the iSCSI Boot Firmware Table
+    pub ibft: bool,
+    pub connected: bool,
+    /// When the node is logged in (e.g., "onboot"); empty if it is not connected
+    pub startup: String,
+}
+
+impl IscsiNode {
+    fn from_dbus(path: String, data: &HashMap<String, OwnedValue>) -> Self {
+        Self {
+            path,
+            target: dict_value(data, "Target").unwrap_or_default(),
+            address: dict_value(data, "Address").unwrap_or_default(),
+            port: dict_value(data, "Port").unwrap_or(DEFAULT_ISCSI_PORT),
+            interface: dict_value(data, "Interface").unwrap_or_default(),
+            ibft: dict_value(data, "IBFT").unwrap_or_default(),
+            connected: dict_value(data, "Connected").unwrap_or_default(),
+            startup: dict_value(data, "Startup").unwrap_or_default(),
+        }
+    }
+
+    /// Whether the node has the given target name and, if they are given, portal address and port
+    pub fn matches(&self, target: &str, address: Option<&str>, port: Option<u32>) -> bool {
+        self.target == target
+            && address.is_none_or(|a| self.address == a)
+            && port.is_none_or(|p| self.port == p)
+    }
+}
+
+/// D-Bus client for the iSCSI initiator
+pub struct IscsiClient<'a> {
+    connection: Connection,
+    initiator_proxy: ISCSIInitiatorProxy<'a>,
+    objects_proxy: ObjectManagerProxy<'a>,
+}
+
+impl<'a> IscsiClient<'a> {
+    pub async fn new(connection: Connection) -> Result<IscsiClient<'a>, ServiceError> {
+        Ok(Self {
+            initiator_proxy: ISCSIInitiatorProxy::new(&connection).await?,
+            objects_proxy: ObjectManagerProxy::builder(&connection)
+                .destination("org.opensuse.Agama.Storage1")?
+                .path("/org/opensuse/Agama/Storage1")?
+                .build()
+                .await?,
+            connection,
+        })
+    }
+
+    /// Returns the iSCSI qualified name of the initiator
+    pub async fn initiator_name(&self) -> Result<String, ServiceError> {
+        Ok(self.initiator_proxy.initiator_name().await?)
+    }
+
+    pub async fn set_initiator_name(&self, name: &str) -> Result<(), ServiceError> {
+        Ok(self.initiator_proxy.set_initiator_name(name).await?)
+    }
+
+    /// Whether the initiator was configured from the iSCSI Boot Firmware Table
+    pub async fn ibft(&self) -> Result<bool, ServiceError> {
+        Ok(self.initiator_proxy.ibft().await?)
+    }
+
+    /// Discovers the targets offered by the given portal
+    ///
+    /// The found targets are added to the list of [nodes](Self::nodes).
+    pub async fn discover(
+        &self,
+        address: &str,
+        port: u32,
+        auth: &IscsiAuth,
+    ) -> Result<(), ServiceError> {
+        let result = self
+            .initiator_proxy
+            .discover(address, port, auth.to_dbus())
+            .await?;
+        if result != 0 {
+            return Err(ServiceError::Failed(format!(
+                "discover the iSCSI targets at {address}:{port}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the known nodes, sorted by target name and portal
+    pub async fn nodes(&self) -> Result<Vec<IscsiNode>, ServiceError> {
+        let objects = self
+            .objects_proxy
+            .get_managed_objects()
+            .await
+            .map_err(zbus::Error::from)?;
+        let mut nodes: Vec<IscsiNode> = objects
+            .into_iter()
+            .filter_map(|(path, interfaces)| {
+                let data = interfaces
+                    .into_iter()
+                    .find(|(name, _)| name.as_str() == NODE_INTERFACE)?
+                    .1;
+                Some(IscsiNode::from_dbus(path.to_string(), &data))
+            })
+            .collect();
+        nodes.sort_by(|a, b| (&a.target, &a.address, a.port).cmp(&(&b.target, &b.address, b.port)));
+        Ok(nodes)
+    }
+
+    /// Returns the node with the given target name and, if they are given, portal address and port
+    pub async fn find_node(
+        &self,
+        target: &str,
+        address: Option<&str>,
+        port: Option<u32>,
+    ) -> Result<IscsiNode, ServiceError> {
+        self.nodes()
+            .await?
+            .into_iter()
+            .find(|n| n.matches(target, address, port))
+            .ok_or_else(|| ServiceError::Failed(format!("find the iSCSI target '{target}'")))
+    }
+
+    /// Logs in the given node
+    pub async fn login(
+        &self,
+        node: &IscsiNode,
+        auth: &IscsiAuth,
+        startup: IscsiStartup,
+    ) -> Result<(), ServiceError> {
+        let startup = startup.to_string();
+        let mut options = auth.to_dbus();
+        options.insert("Startup", Value::new(startup.as_str()));
+        let result = self.node_proxy(node).await?.login(options).await?;
+        if result != 0 {
+            return Err(ServiceError::Failed(format!(
+                "log in the iSCSI target '{}'",
+                node.target
+            )));
+        }
+        Ok(())
+    }
+
+    /// Logs out the given node
+    pub async fn logout(&self, node: &IscsiNode) -> Result<(), ServiceError> {
+        let result = self.node_proxy(node).await?.logout().await?;
+        if result != 0 {
+            return Err(ServiceError::Failed(format!(
+                "log out the iSCSI target '{}'",
+                node.target
+            )));
+        }
+        Ok(())
+    }
+
+    async fn node_proxy(&self, node: &IscsiNode) -> Result<ISCSINodeProxy<'static>, ServiceError> {
+        Ok(ISCSINodeProxy::builder(&self.connection)
+            .path(node.path.clone())?
+            .build()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::{Arc, Mutex};
+    use zbus::zvariant::OwnedObjectPath;
+    use zbus::{dbus_interface, ConnectionBuilder, Guid};
+
+    const TARGET: &str = "iqn.2023-01.com.example:storage";
+    const NODE_PATH: &str = "/org/opensuse/Agama/Storage1/iscsi_nodes/1";
+
+    /// State shared by the objects of the mock service
+    #[derive(Default)]
+    struct MockState {
+        initiator_name: String,
+        discovered: bool,
+        connected: bool,
+        startup: String,
+    }
+
+    type SharedState = Arc<Mutex<MockState>>;
+
+    fn option(options: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+        dict_value(options, key)
+    }
+
+    struct MockInitiator(SharedState);
+
+    #[dbus_interface(name = "org.opensuse.Agama.Storage1.ISCSI.Initiator")]
+    impl MockInitiator {
+        fn discover(&self, address: &str, port: u32, options: HashMap<String, OwnedValue>) -> u32 {
+            if address != "192.168.1.10" || port != DEFAULT_ISCSI_PORT {
+                return 1;
+            }
+            if option(&options, "Username").as_deref() != Some("jane") {
+                return 2;
+            }
+            self.0.lock().unwrap().discovered = true;
+            0
+        }
+
+        #[dbus_interface(property)]
+        fn initiator_name(&self) -> String {
+            self.0.lock().unwrap().initiator_name.clone()
+        }
+
+        #[dbus_interface(property)]
+        fn set_initiator_name(&mut self, value: &str) {
+            self.0.lock().unwrap().initiator_name = value.to_string();
+        }
+    }
+
+    struct MockNode(SharedState);
+
+    #[dbus_interface(name = "org.opensuse.Agama.Storage1.ISCSI.Node")]
+    impl MockNode {
+        fn login(&self, options: HashMap<String, OwnedValue>) -> u32 {
+            let mut state = self.0.lock().unwrap();
+            if option(&options, "Password").as_deref() != Some("s3cr3t") {
+                return 1;
+            }
+            state.connected = true;
+            state.startup = option(&options, "Startup").unwrap_or_default();
+            0
+        }
+
+        fn logout(&self) -> u32 {
+            let mut state = self.0.lock().unwrap();
+            state.connected = false;
+            state.startup = String::new();
+            0
+        }
+    }
+
+    struct MockObjectManager(SharedState);
+
+    #[dbus_interface(name = "org.freedesktop.DBus.ObjectManager")]
+    impl MockObjectManager {
+        fn get_managed_objects(
+            &self,
+        ) -> HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>> {
+            let state = self.0.lock().unwrap();
+            if !state.discovered {
+                return HashMap::new();
+            }
+            let node = HashMap::from([
+                ("Target".to_string(), Value::new(TARGET).into()),
+                ("Address".to_string(), Value::new("192.168.1.10").into()),
+                ("Port".to_string(), Value::new(DEFAULT_ISCSI_PORT).into()),
+                ("Interface".to_string(), Value::new("default").into()),
+                ("IBFT".to_string(), Value::new(false).into()),
+                ("Connected".to_string(), Value::new(state.connected).into()),
+                (
+                    "Startup".to_string(),
+                    Value::new(state.startup.as_str()).into(),
+                ),
+            ]);
+            HashMap::from([(
+                OwnedObjectPath::try_from(NODE_PATH).unwrap(),
+                HashMap::from([(NODE_INTERFACE.to_string(), node)]),
+            )])
+        }
+    }
+
+    /// Connects a client to a mock iSCSI service using a peer-to-peer connection
+    async fn mock_service(state: SharedState) -> (Connection, Connection) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let guid = Guid::generate();
+        let server = ConnectionBuilder::unix_stream(server_stream)
+            .server(&guid)
+            .p2p()
+            .serve_at("/org/opensuse/Agama/Storage1", MockInitiator(state.clone()))
+            .unwrap()
+            .serve_at(
+                "/org/opensuse/Agama/Storage1",
+                MockObjectManager(state.clone()),
+            )
+            .unwrap()
+            .serve_at(NODE_PATH, MockNode(state))
+            .unwrap()
+            .build();
+        let client = ConnectionBuilder::unix_stream(client_stream).p2p().build();
+        let (server, client) = futures::join!(Box::pin(server), Box::pin(client));
+        (server.unwrap(), client.unwrap())
+    }
+
+    #[test]
+    fn test_iscsi_client() {
+        async_std::task::block_on(async {
+            let state = SharedState::default();
+            let (_server, connection) = mock_service(state.clone()).await;
+            let client = IscsiClient::new(connection).await.unwrap();
+
+            client
+                .set_initiator_name("iqn.2023-01.com.example:initiator")
+                .await
+                .unwrap();
+            assert_eq!(
+                state.lock().unwrap().initiator_name,
+                "iqn.2023-01.com.example:initiator"
+            );
+            assert!(client.nodes().await.unwrap().is_empty());
+
+            let mut auth = IscsiAuth::default();
+            let error = client
+                .discover("192.168.1.10", DEFAULT_ISCSI_PORT, &auth)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "Could not discover the iSCSI targets at 192.168.1.10:3260"
+            );
+
+            auth.username = Some("jane".to_string());
+            auth.password = Some("s3cr3t".to_string());
+            client
+                .discover("192.168.1.10", DEFAULT_ISCSI_PORT, &auth)
+                .await
+                .unwrap();
+            let node = client
+                .find_node(TARGET, Some("192.168.1.10"), None)
+                .await
+                .unwrap();
+            assert!(!node.connected);
+            assert!(client
+                .find_node(TARGET, Some("192.168.1.11"), None)
+                .await
+                .is_err());
+
+            client
+                .login(&node, &auth, IscsiStartup::Onboot)
+                .await
+                .unwrap();
+            let node = client.find_node(TARGET, None, None).await.unwrap();
+            assert!(node.connected);
+            assert_eq!(node.startup, "onboot");
+
+            client.logout(&node).await.unwrap();
+            assert!(!state.lock().unwrap().connected);
+        });
+    }
+}
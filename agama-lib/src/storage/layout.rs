@@ -0,0 +1,433 @@
+//! Expert storage layout
+//!
+//! Besides the guided proposal, the storage settings can describe software RAIDs, LVM volume
+//! groups and logical volumes. The layout is checked against the available devices before
+//! sending it to the storage service, so the obvious mistakes (unknown devices, logical volumes
+//! which do not fit, etc.) are reported early.
+use super::{dict_value, DiskSize, StorageDevice};
+use crate::install_settings::ExpertStorageSettings;
+use crate::settings::{ConversionError, SettingObject, SettingValue, SettingsError};
+use agama_derive::SettingEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+use zbus::zvariant::{OwnedValue, Value};
+
+/// Software RAID level
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema, SettingEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RaidLevel {
+    Raid0,
+    Raid1,
+    Raid5,
+    Raid6,
+    Raid10,
+}
+
+impl RaidLevel {
+    /// Minimum number of devices
+    pub fn min_devices(&self) -> usize {
+        match self {
+            Self::Raid0 | Self::Raid1 => 2,
+            Self::Raid5 => 3,
+            Self::Raid6 | Self::Raid10 => 4,
+        }
+    }
+
+    /// Usable size of a RAID built from devices of the given sizes
+    ///
+    /// ```
+    /// # use agama_lib::storage::{DiskSize, RaidLevel};
+    /// let gib = |n: u64| DiskSize::from_bytes(n * 1024 * 1024 * 1024);
+    /// assert_eq!(RaidLevel::Raid1.capacity(&[gib(100), gib(120)]), gib(100));
+    /// assert_eq!(RaidLevel::Raid5.capacity(&[gib(100), gib(100), gib(100)]), gib(200));
+    /// ```
+    pub fn capacity(&self, sizes: &[DiskSize]) -> DiskSize {
+        let count = sizes.len() as u64;
+        let smallest = sizes.iter().map(|s| s.bytes()).min().unwrap_or_default();
+        let data_devices = match self {
+            Self::Raid0 => count,
+            Self::Raid1 => count.min(1),
+            Self::Raid5 => count.saturating_sub(1),
+            Self::Raid6 => count.saturating_sub(2),
+            Self::Raid10 => count / 2,
+        };
+        DiskSize::from_bytes(smallest * data_devices)
+    }
+}
+
+/// Software RAID
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct Raid {
+    /// RAID device name (e.g., "/dev/md0")
+    pub name: String,
+    /// RAID level (raid0, raid1, raid5, raid6 or raid10)
+    pub level: RaidLevel,
+    /// Disks or partitions to build the RAID from
+    pub devices: Vec<String>,
+}
+
+/// LVM volume group
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct VolumeGroup {
+    /// Volume group name (e.g., "system")
+    pub name: String,
+    /// Physical volumes: disks, partitions or RAIDs
+    pub devices: Vec<String>,
+}
+
+/// LVM logical volume
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct LogicalVolume {
+    /// Logical volume name (e.g., "root")
+    pub name: String,
+    /// Name of the volume group
+    pub volume_group: String,
+    pub size: DiskSize,
+    /// Where to mount the file system (e.g., "/var")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mount_path: Option<String>,
+    /// File system type (e.g., "xfs")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filesystem: Option<String>,
+}
+
+impl TryFrom<SettingObject> for Raid {
+    type Error = SettingsError;
+
+    fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
+        Ok(Raid {
+            name: value.required("name")?.try_into()?,
+            level: value.required("level")?.try_into()?,
+            devices: value.required("devices")?.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SettingObject> for VolumeGroup {
+    type Error = SettingsError;
+
+    fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
+        Ok(VolumeGroup {
+            name: value.required("name")?.try_into()?,
+            devices: value.required("devices")?.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SettingObject> for LogicalVolume {
+    type Error = SettingsError;
+
+    fn try_from(value: SettingObject) -> Result<Self, Self::Error> {
+        Ok(LogicalVolume {
+            name: value.required("name")?.try_into()?,
+            volume_group: value.required("volumeGroup")?.try_into()?,
+            size: value.required("size")?.try_into()?,
+            mount_path: value.get("mountPath").map(|v| v.0),
+            filesystem: value.get("filesystem").map(|v| v.0),
+        })
+    }
+}
+
+/// Error found when checking a storage layout
+#[derive(Debug, Error, PartialEq)]
+pub enum LayoutError {
+    #[error("Device '{0}' does not exist")]
+    UnknownDevice(String),
+    #[error("Device '{0}' is used more than once")]
+    DeviceInUse(String),
+    #[error("'{0}' is defined more than once")]
+    DuplicatedName(String),
+    #[error("RAID '{0}' needs at least {1} devices")]
+    NotEnoughDevices(String, usize),
+    #[error("Volume group '{1}' of logical volume '{0}' is not defined")]
+    UnknownVolumeGroup(String, String),
+    #[error("The logical volumes of '{0}' need {1}, but only {2} are available")]
+    NotEnoughSpace(String, DiskSize, DiskSize),
+}
+
+/// Checks the layout against the available devices
+///
+/// The sizes are only checked when the sizes of all the physical volumes are known.
+pub fn validate_layout(
+    layout: &ExpertStorageSettings,
+    available: &[StorageDevice],
+) -> Result<(), LayoutError> {
+    let mut sizes: HashMap<&str, Option<DiskSize>> = HashMap::new();
+    let mut pending = available.iter().collect::<Vec<_>>();
+    while let Some(device) = pending.pop() {
+        sizes.insert(&device.name, device.size);
+        pending.extend(&device.partitions);
+    }
+
+    let mut names = HashSet::new();
+    let mut used = HashSet::new();
+    let mut use_device = |name: &str| {
+        if !used.insert(name.to_string()) {
+            return Err(LayoutError::DeviceInUse(name.to_string()));
+        }
+        Ok(())
+    };
+
+    for raid in &layout.raids {
+        if !names.insert(raid.name.as_str()) || sizes.contains_key(raid.name.as_str()) {
+            return Err(LayoutError::DuplicatedName(raid.name.clone()));
+        }
+        if raid.devices.len() < raid.level.min_devices() {
+            return Err(LayoutError::NotEnoughDevices(
+                raid.name.clone(),
+                raid.level.min_devices(),
+            ));
+        }
+
+        let mut member_sizes = vec![];
+        for device in &raid.devices {
+            let size = sizes
+                .get(device.as_str())
+                .ok_or_else(|| LayoutError::UnknownDevice(device.clone()))?;
+            member_sizes.push(*size);
+            use_device(device)?;
+        }
+        let size: Option<Vec<_>> = member_sizes.into_iter().collect();
+        sizes.insert(&raid.name, size.map(|s| raid.level.capacity(&s)));
+    }
+
+    let mut capacities = HashMap::new();
+    for volume_group in &layout.volume_groups {
+        if !names.insert(volume_group.name.as_str()) {
+            return Err(LayoutError::DuplicatedName(volume_group.name.clone()));
+        }
+
+        let mut capacity = Some(0);
+        for device in &volume_group.devices {
+            let size = sizes
+                .get(device.as_str())
+                .ok_or_else(|| LayoutError::UnknownDevice(device.clone()))?;
+            capacity = capacity.zip(*size).map(|(c, s)| c + s.bytes());
+            use_device(device)?;
+        }
+        capacities.insert(volume_group.name.as_str(), capacity);
+    }
+
+    let mut needed: HashMap<&str, u64> = HashMap::new();
+    for volume in &layout.logical_volumes {
+        if !capacities.contains_key(volume.volume_group.as_str()) {
+            return Err(LayoutError::UnknownVolumeGroup(
+                volume.name.clone(),
+                volume.volume_group.clone(),
+            ));
+        }
+        *needed.entry(&volume.volume_group).or_default() += volume.size.bytes();
+    }
+
+    for volume_group in &layout.volume_groups {
+        let name = volume_group.name.as_str();
+        let needed = needed.get(name).copied().unwrap_or_default();
+        if let Some(Some(capacity)) = capacities.get(name) {
+            if needed > *capacity {
+                return Err(LayoutError::NotEnoughSpace(
+                    name.to_string(),
+                    DiskSize::from_bytes(needed),
+                    DiskSize::from_bytes(*capacity),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds the settings of the layout for the D-Bus service
+pub(crate) fn layout_to_dbus(layout: &ExpertStorageSettings) -> Vec<(&str, Value<'_>)> {
+    let raids: Vec<HashMap<&str, Value<'_>>> = layout
+        .raids
+        .iter()
+        .map(|r| {
+            HashMap::from([
+                ("Name", Value::new(r.name.as_str())),
+                ("Level", Value::new(r.level.to_string())),
+                ("Devices", Value::new(r.devices.clone())),
+            ])
+        })
+        .collect();
+    let volume_groups: Vec<HashMap<&str, Value<'_>>> = layout
+        .volume_groups
+        .iter()
+        .map(|v| {
+            HashMap::from([
+                ("Name", Value::new(v.name.as_str())),
+                ("Devices", Value::new(v.devices.clone())),
+            ])
+        })
+        .collect();
+    let logical_volumes: Vec<HashMap<&str, Value<'_>>> = layout
+        .logical_volumes
+        .iter()
+        .map(|l| {
+            let mut volume = HashMap::from([
+                ("Name", Value::new(l.name.as_str())),
+                ("VolumeGroup", Value::new(l.volume_group.as_str())),
+                ("Size", Value::new(l.size.bytes())),
+            ]);
+            if let Some(path) = &l.mount_path {
+                volume.insert("MountPath", Value::new(path.as_str()));
+            }
+            if let Some(filesystem) = &l.filesystem {
+                volume.insert("Filesystem", Value::new(filesystem.as_str()));
+            }
+            volume
+        })
+        .collect();
+
+    vec![
+        ("Raids", Value::new(raids)),
+        ("VolumeGroups", Value::new(volume_groups)),
+        ("LogicalVolumes", Value::new(logical_volumes)),
+    ]
+}
+
+/// Reads a layout from the D-Bus service, ignoring the incomplete elements
+pub(crate) fn layout_from_dbus(
+    raids: &[HashMap<String, OwnedValue>],
+    volume_groups: &[HashMap<String, OwnedValue>],
+    logical_volumes: &[HashMap<String, OwnedValue>],
+) -> ExpertStorageSettings {
+    ExpertStorageSettings {
+        raids: raids
+            .iter()
+            .filter_map(|r| {
+                let level: String = dict_value(r, "Level")?;
+                Some(Raid {
+                    name: dict_value(r, "Name")?,
+                    level: SettingValue(level).try_into().ok()?,
+                    devices: dict_value(r, "Devices")?,
+                })
+            })
+            .collect(),
+        volume_groups: volume_groups
+            .iter()
+            .filter_map(|v| {
+                Some(VolumeGroup {
+                    name: dict_value(v, "Name")?,
+                    devices: dict_value(v, "Devices")?,
+                })
+            })
+            .collect(),
+        logical_volumes: logical_volumes
+            .iter()
+            .filter_map(|l| {
+                Some(LogicalVolume {
+                    name: dict_value(l, "Name")?,
+                    volume_group: dict_value(l, "VolumeGroup")?,
+                    size: DiskSize::from_bytes(dict_value(l, "Size")?),
+                    mount_path: dict_value(l, "MountPath"),
+                    filesystem: dict_value(l, "Filesystem"),
+                })
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gib(n: u64) -> DiskSize {
+        DiskSize::from_bytes(n * 1024 * 1024 * 1024)
+    }
+
+    fn disk(name: &str, size: u64) -> StorageDevice {
+        StorageDevice {
+            name: name.to_string(),
+            size: Some(gib(size)),
+            ..Default::default()
+        }
+    }
+
+    fn layout(volume_size: u64) -> ExpertStorageSettings {
+        ExpertStorageSettings {
+            raids: vec![Raid {
+                name: "/dev/md0".to_string(),
+                level: RaidLevel::Raid1,
+                devices: vec!["/dev/sda".to_string(), "/dev/sdb".to_string()],
+            }],
+            volume_groups: vec![VolumeGroup {
+                name: "system".to_string(),
+                devices: vec!["/dev/md0".to_string()],
+            }],
+            logical_volumes: vec![LogicalVolume {
+                name: "root".to_string(),
+                volume_group: "system".to_string(),
+                size: gib(volume_size),
+                mount_path: Some("/".to_string()),
+                filesystem: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_validate_layout() {
+        let available = vec![disk("/dev/sda", 100), disk("/dev/sdb", 120)];
+        assert_eq!(validate_layout(&layout(100), &available), Ok(()));
+        assert_eq!(
+            validate_layout(&layout(101), &available)
+                .unwrap_err()
+                .to_string(),
+            "The logical volumes of 'system' need 101 GiB, but only 100 GiB are available"
+        );
+
+        let mut wrong = layout(10);
+        wrong.raids[0].devices[1] = "/dev/sdc".to_string();
+        assert_eq!(
+            validate_layout(&wrong, &available),
+            Err(LayoutError::UnknownDevice("/dev/sdc".to_string()))
+        );
+
+        let mut wrong = layout(10);
+        wrong.volume_groups[0].devices.push("/dev/sda".to_string());
+        assert_eq!(
+            validate_layout(&wrong, &available),
+            Err(LayoutError::DeviceInUse("/dev/sda".to_string()))
+        );
+
+        let mut wrong = layout(10);
+        wrong.raids[0].level = RaidLevel::Raid5;
+        assert_eq!(
+            validate_layout(&wrong, &available),
+            Err(LayoutError::NotEnoughDevices("/dev/md0".to_string(), 3))
+        );
+
+        let mut wrong = layout(10);
+        wrong.logical_volumes[0].volume_group = "data".to_string();
+        assert_eq!(
+            validate_layout(&wrong, &available),
+            Err(LayoutError::UnknownVolumeGroup(
+                "root".to_string(),
+                "data".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_layout_from_object() {
+        let object = SettingObject::from([
+            ("name", "/dev/md0"),
+            ("level", "raid1"),
+            ("devices", "/dev/sda,/dev/sdb"),
+        ]);
+        let raid = Raid::try_from(object).unwrap();
+        assert_eq!(raid.devices, vec!["/dev/sda", "/dev/sdb"]);
+
+        let object = SettingObject::from([("name", "root"), ("volumeGroup", "system")]);
+        let error = LogicalVolume::try_from(object)
+            .unwrap_err()
+            .in_section("logical_volumes");
+        assert_eq!(error.to_string(), "Missing 'size' for 'logical_volumes'");
+    }
+}
@@ -0,0 +1,358 @@
+//! Device selectors
+//!
+//! Instead of giving the name of a device (e.g., `"/dev/sda"`), a profile can describe it with a
+//! set of criteria: `{"biggest": true}`, `{"size": ">= 100 GiB"}`, `{"model": "Samsung*"}`, etc.
+//! The selectors are resolved against the available devices when the settings are stored.
+use super::{DiskSize, StorageDevice};
+use crate::settings::{ConversionError, SettingValue};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Criteria to select a device
+///
+/// A device must meet all the given criteria. If `biggest` is set, the biggest of the matching
+/// devices is selected; otherwise, exactly one device must match.
+///
+/// ```
+/// # use agama_lib::storage::DeviceSelector;
+/// let selector: DeviceSelector = serde_json::from_str(r#"{"model": "Samsung*"}"#).unwrap();
+/// assert_eq!(selector.model.as_deref(), Some("Samsung*"));
+/// assert!(!selector.is_empty());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DeviceSelector {
+    /// Whether to select the biggest of the matching devices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub biggest: Option<bool>,
+    /// Size of the device (e.g., ">= 100 GiB")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<SizeConstraint>,
+    /// Model of the device; `*` and `?` can be used as wildcards (e.g., "Samsung*")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Serial number of the device; `*` and `?` can be used as wildcards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Transport of the device (e.g., "nvme", "usb" or "sata")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<String>,
+}
+
+impl DeviceSelector {
+    /// Whether no criteria is given
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether the given device meets the criteria, except `biggest`
+    pub fn matches(&self, device: &StorageDevice) -> bool {
+        let text_matches = |pattern: &Option<String>, value: &Option<String>| match pattern {
+            Some(pattern) => value.as_deref().is_some_and(|v| wildcard_match(pattern, v)),
+            None => true,
+        };
+
+        let size_matches = match &self.size {
+            Some(constraint) => device.size.is_some_and(|s| constraint.matches(s)),
+            None => true,
+        };
+        let transport_matches = match &self.transport {
+            Some(transport) => device
+                .transport
+                .as_deref()
+                .is_some_and(|t| t.eq_ignore_ascii_case(transport)),
+            None => true,
+        };
+        size_matches
+            && transport_matches
+            && text_matches(&self.model, &device.model)
+            && text_matches(&self.serial, &device.serial)
+    }
+
+    /// Selects a device from the given ones
+    pub fn select<'a>(
+        &self,
+        devices: &'a [StorageDevice],
+    ) -> Result<&'a StorageDevice, SelectorError> {
+        let candidates: Vec<_> = devices.iter().filter(|d| self.matches(d)).collect();
+        if self.biggest == Some(true) {
+            return candidates
+                .into_iter()
+                .max_by_key(|d| d.size)
+                .ok_or_else(|| SelectorError::NoMatch(self.to_string()));
+        }
+
+        match candidates.as_slice() {
+            [] => Err(SelectorError::NoMatch(self.to_string())),
+            [device] => Ok(device),
+            _ => {
+                let names: Vec<_> = candidates.iter().map(|d| d.name.as_str()).collect();
+                Err(SelectorError::Ambiguous(self.to_string(), names.join(", ")))
+            }
+        }
+    }
+}
+
+/// Shows the selector as JSON (e.g., `{"size":">= 100 GiB"}`)
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        write!(f, "{json}")
+    }
+}
+
+/// Error resolving a device selector
+#[derive(Debug, Error, PartialEq)]
+pub enum SelectorError {
+    #[error("A device needs a name or some criteria to select it")]
+    Empty,
+    #[error("No device matches {0}")]
+    NoMatch(String),
+    #[error("Several devices match {0} ({1}). Please, use a more specific selector")]
+    Ambiguous(String, String),
+}
+
+/// Comparison operator of a size constraint
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SizeOperator {
+    Equal,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl SizeOperator {
+    const ALL: [(&'static str, SizeOperator); 5] = [
+        (">=", SizeOperator::GreaterOrEqual),
+        ("<=", SizeOperator::LessOrEqual),
+        (">", SizeOperator::Greater),
+        ("<", SizeOperator::Less),
+        ("=", SizeOperator::Equal),
+    ];
+
+    fn symbol(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, op)| op == self)
+            .map(|(symbol, _)| *symbol)
+            .unwrap_or("=")
+    }
+}
+
+/// Size constraint for a device (e.g., `">= 100 GiB"`)
+///
+/// The supported operators are `>=`, `<=`, `>`, `<` and `=`. A size without operator must match
+/// exactly.
+///
+/// ```
+/// # use agama_lib::storage::SizeConstraint;
+/// let constraint: SizeConstraint = ">= 100 GiB".parse().unwrap();
+/// assert!(constraint.matches("1 TiB".parse().unwrap()));
+/// assert!(!constraint.matches("64 GiB".parse().unwrap()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SizeConstraint {
+    operator: SizeOperator,
+    size: DiskSize,
+}
+
+impl SizeConstraint {
+    /// Whether the given size meets the constraint
+    pub fn matches(&self, size: DiskSize) -> bool {
+        match self.operator {
+            SizeOperator::Equal => size == self.size,
+            SizeOperator::Greater => size > self.size,
+            SizeOperator::GreaterOrEqual => size >= self.size,
+            SizeOperator::Less => size < self.size,
+            SizeOperator::LessOrEqual => size <= self.size,
+        }
+    }
+}
+
+/// Error parsing a size constraint
+#[derive(Debug, Error, PartialEq)]
+#[error("not a valid size constraint")]
+pub struct ParseSizeConstraintError;
+
+impl FromStr for SizeConstraint {
+    type Err = ParseSizeConstraintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (operator, size) = SizeOperator::ALL
+            .iter()
+            .find_map(|(symbol, op)| s.strip_prefix(symbol).map(|size| (*op, size)))
+            .unwrap_or((SizeOperator::Equal, s));
+        let size = size.parse().map_err(|_| ParseSizeConstraintError)?;
+        Ok(Self { operator, size })
+    }
+}
+
+impl fmt::Display for SizeConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.operator.symbol(), self.size)
+    }
+}
+
+impl Serialize for SizeConstraint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SizeConstraint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse()
+            .map_err(|_| de::Error::custom(format!("not a valid size constraint: '{text}'")))
+    }
+}
+
+impl JsonSchema for SizeConstraint {
+    fn schema_name() -> String {
+        "SizeConstraint".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            ..Default::default()
+        };
+        schema.metadata().description = Some(
+            "Size with an optional operator: >=, <=, >, < or = (e.g., \">= 100 GiB\")".to_string(),
+        );
+        schema.into()
+    }
+}
+
+impl TryFrom<SettingValue> for SizeConstraint {
+    type Error = ConversionError;
+
+    fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+        value
+            .0
+            .parse()
+            .map_err(|_| ConversionError::new("not a valid size constraint", &value))
+    }
+}
+
+impl TryFrom<SettingValue> for Option<SizeConstraint> {
+    type Error = ConversionError;
+
+    fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+        Ok(Some(value.try_into()?))
+    }
+}
+
+/// Matches a text against a pattern where `*` stands for any text and `?` for any character
+///
+/// The comparison is case-insensitive.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    // position of the last `*` in the pattern and of the text it was matched against
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, size: &str, model: &str, transport: &str) -> StorageDevice {
+        StorageDevice {
+            name: name.to_string(),
+            size: size.parse().ok(),
+            model: Some(model.to_string()),
+            transport: Some(transport.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn devices() -> Vec<StorageDevice> {
+        vec![
+            device("/dev/sda", "500 GiB", "Samsung SSD 870", "sata"),
+            device("/dev/sdb", "2 TiB", "WDC WD20EZRZ", "sata"),
+            device("/dev/nvme0n1", "1 TiB", "Samsung SSD 980", "nvme"),
+        ]
+    }
+
+    fn select(json: &str) -> Result<String, SelectorError> {
+        let selector: DeviceSelector = serde_json::from_str(json).unwrap();
+        selector.select(&devices()).map(|d| d.name.clone())
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select(r#"{"biggest": true}"#).unwrap(), "/dev/sdb");
+        assert_eq!(select(r#"{"transport": "NVMe"}"#).unwrap(), "/dev/nvme0n1");
+        assert_eq!(select(r#"{"size": "< 1 TiB"}"#).unwrap(), "/dev/sda");
+        assert_eq!(
+            select(r#"{"model": "samsung*", "biggest": true}"#).unwrap(),
+            "/dev/nvme0n1"
+        );
+    }
+
+    #[test]
+    fn test_select_errors() {
+        assert_eq!(
+            select(r#"{"transport": "usb"}"#),
+            Err(SelectorError::NoMatch(r#"{"transport":"usb"}"#.to_string()))
+        );
+        assert_eq!(
+            select(r#"{"size": ">= 1 TiB"}"#).unwrap_err().to_string(),
+            "Several devices match {\"size\":\">= 1 TiB\"} (/dev/sdb, /dev/nvme0n1). \
+             Please, use a more specific selector"
+        );
+    }
+
+    #[test]
+    fn test_size_constraint() {
+        let constraint: SizeConstraint = ">=100GiB".parse().unwrap();
+        assert_eq!(constraint.to_string(), ">= 100 GiB");
+        let constraint: SizeConstraint = "1 TiB".parse().unwrap();
+        assert_eq!(constraint.to_string(), "= 1 TiB");
+        assert_eq!(
+            "~ 1 TiB".parse::<SizeConstraint>(),
+            Err(ParseSizeConstraintError)
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("Samsung*", "Samsung SSD 980"));
+        assert!(wildcard_match("*SSD*", "Samsung SSD 980"));
+        assert!(wildcard_match("WD?0EZRZ", "WD20EZRZ"));
+        assert!(!wildcard_match("Samsung", "Samsung SSD 980"));
+        assert!(!wildcard_match("*SSD", "Samsung SSD 980"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Disk sizes
+//!
+//! Sizes are expressed as a number and a unit (e.g., `"20 GiB"` or `"1.5 TB"`). Binary (KiB,
+//! MiB, etc.) and decimal (KB, MB, etc.) units are supported. The short forms (`"K"`, `"M"`,
+//! etc.) are interpreted as binary units and a number without unit means bytes.
+use crate::settings::{ConversionError, SettingValue};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, SingleOrVec};
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+const BINARY_UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+const DECIMAL_UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+
+/// Size of a disk, partition, etc.
+///
+/// ```
+/// # use agama_lib::storage::DiskSize;
+/// let size: DiskSize = "20 GiB".parse().unwrap();
+/// assert_eq!(size.bytes(), 20 * 1024 * 1024 * 1024);
+/// assert_eq!(size.to_string(), "20 GiB");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DiskSize(u64);
+
+impl DiskSize {
+    pub fn from_bytes(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Error parsing a disk size
+#[derive(Debug, Error, PartialEq)]
+#[error("not a valid disk size")]
+pub struct ParseDiskSizeError;
+
+impl FromStr for DiskSize {
+    type Err = ParseDiskSizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let index = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(index);
+        let number: f64 = number.parse().map_err(|_| ParseDiskSizeError)?;
+        let multiplier = unit_multiplier(unit.trim()).ok_or(ParseDiskSizeError)?;
+        let bytes = number * multiplier as f64;
+        if bytes > u64::MAX as f64 {
+            return Err(ParseDiskSizeError);
+        }
+        Ok(Self(bytes.round() as u64))
+    }
+}
+
+/// Returns the number of bytes of the given unit
+fn unit_multiplier(unit: &str) -> Option<u64> {
+    if unit.is_empty() || unit.eq_ignore_ascii_case("b") {
+        return Some(1);
+    }
+
+    let short = ["k", "m", "g", "t", "p"];
+    for (exp, ((binary, decimal), short)) in BINARY_UNITS
+        .iter()
+        .zip(DECIMAL_UNITS)
+        .zip(short)
+        .enumerate()
+    {
+        let exp = exp as u32 + 1;
+        if unit.eq_ignore_ascii_case(binary) || unit.eq_ignore_ascii_case(short) {
+            return Some(1024_u64.pow(exp));
+        }
+        if unit.eq_ignore_ascii_case(decimal) {
+            return Some(1000_u64.pow(exp));
+        }
+    }
+    None
+}
+
+/// Uses the biggest binary unit which represents the size exactly
+impl fmt::Display for DiskSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (exp, unit) in BINARY_UNITS.iter().enumerate().rev() {
+            let multiplier = 1024_u64.pow(exp as u32 + 1);
+            if self.0 >= multiplier && self.0.is_multiple_of(multiplier) {
+                return write!(f, "{} {}", self.0 / multiplier, unit);
+            }
+        }
+        write!(f, "{} B", self.0)
+    }
+}
+
+impl Serialize for DiskSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Accepts a string (e.g., `"20 GiB"`) or a number of bytes
+impl<'de> Deserialize<'de> for DiskSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawSize {
+            Bytes(u64),
+            Text(String),
+        }
+
+        match RawSize::deserialize(deserializer)? {
+            RawSize::Bytes(bytes) => Ok(Self(bytes)),
+            RawSize::Text(text) => text
+                .parse()
+                .map_err(|_| de::Error::custom(format!("not a valid disk size: '{text}'"))),
+        }
+    }
+}
+
+impl JsonSchema for DiskSize {
+    fn schema_name() -> String {
+        "DiskSize".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = SchemaObject {
+            instance_type: Some(SingleOrVec::Vec(vec![
+                InstanceType::String,
+                InstanceType::Integer,
+            ])),
+            ..Default::default()
+        };
+        schema.metadata().description =
+            Some("Size in bytes or with a unit (e.g., \"20 GiB\")".to_string());
+        schema.into()
+    }
+}
+
+impl TryFrom<SettingValue> for DiskSize {
+    type Error = ConversionError;
+
+    fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+        value
+            .0
+            .parse()
+            .map_err(|_| ConversionError::new("not a valid disk size", &value))
+    }
+}
+
+impl TryFrom<SettingValue> for Option<DiskSize> {
+    type Error = ConversionError;
+
+    fn try_from(value: SettingValue) -> Result<Self, Self::Error> {
+        Ok(Some(value.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let size = |s: &str| s.parse::<DiskSize>().map(|s| s.bytes());
+        assert_eq!(size("512"), Ok(512));
+        assert_eq!(size("1 KiB"), Ok(1024));
+        assert_eq!(size("1.5GiB"), Ok(1536 * 1024 * 1024));
+        assert_eq!(size("2 gb"), Ok(2_000_000_000));
+        assert_eq!(size("10G"), Ok(10 * 1024 * 1024 * 1024));
+        assert_eq!(size("GiB"), Err(ParseDiskSizeError));
+        assert_eq!(size("10 parsecs"), Err(ParseDiskSizeError));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            DiskSize::from_bytes(1536 * 1024 * 1024).to_string(),
+            "1536 MiB"
+        );
+        assert_eq!(DiskSize::from_bytes(1000).to_string(), "1000 B");
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let size: DiskSize = serde_json::from_str("\"20 GiB\"").unwrap();
+        assert_eq!(size.to_string(), "20 GiB");
+        let size: DiskSize = serde_json::from_str("1024").unwrap();
+        assert_eq!(size.to_string(), "1 KiB");
+        assert!(serde_json::from_str::<DiskSize>("\"big\"").is_err());
+    }
+}
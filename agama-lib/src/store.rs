@@ -1,15 +1,61 @@
+mod bootloader;
+mod iscsi;
+mod localization;
 mod software;
 mod storage;
 mod users;
 
 use crate::error::ServiceError;
-use crate::install_settings::{InstallSettings, Scope};
+use crate::install_settings::{InstallSettings, Scope, SoftwareSettings};
+use crate::settings::{Settings, SettingsError};
+use crate::software::ResolvableType;
+use crate::store::bootloader::BootloaderStore;
+use crate::store::iscsi::IscsiStore;
+use crate::store::localization::LocalizationStore;
 use crate::store::software::SoftwareStore;
 use crate::store::storage::StorageStore;
 use crate::store::users::UsersStore;
 use std::error::Error;
 use zbus::Connection;
 
+/// How to unset a setting in the D-Bus service
+///
+/// The stores only write the values which are set, so most settings need an explicit action.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UnsetAction {
+    /// Write the whole section again without the setting (e.g., the storage proposal)
+    Rewrite(Scope),
+    RemoveFirstUser,
+    RemoveRootPassword,
+    RemoveRootSshKey,
+    Unselect(ResolvableType),
+    LogoutIscsiTargets,
+}
+
+impl UnsetAction {
+    /// Returns the action to unset the given setting
+    ///
+    /// The settings which always have a value in the installer (e.g., the product) cannot be
+    /// unset.
+    ///
+    /// * `attr`: setting to unset (e.g., `"software.patterns"`).
+    fn for_setting(attr: &str) -> Result<Self, SettingsError> {
+        let scope =
+            Scope::from_key(attr).ok_or_else(|| SettingsError::UnknownKey(attr.to_string()))?;
+        let action = match (scope, attr) {
+            (Scope::Users, "user.user_name") => Self::RemoveFirstUser,
+            (Scope::Users, "root.password") => Self::RemoveRootPassword,
+            (Scope::Users, "root.ssh_public_key") => Self::RemoveRootSshKey,
+            (Scope::Software, "software.patterns") => Self::Unselect(ResolvableType::Pattern),
+            (Scope::Software, "software.packages") => Self::Unselect(ResolvableType::Package),
+            (Scope::Iscsi, "iscsi.targets") => Self::LogoutIscsiTargets,
+            (Scope::Users | Scope::Storage | Scope::Bootloader, _) => Self::Rewrite(scope),
+            _ => return Err(SettingsError::CannotUnset(attr.to_string())),
+        };
+        Ok(action)
+    }
+}
+
 /// Loading and storing the settings in the D-Bus service
 ///
 /// This struct uses the default connection built by [connection function](super::connection).
@@ -17,6 +63,9 @@ pub struct Store<'a> {
     users: UsersStore<'a>,
     software: SoftwareStore<'a>,
     storage: StorageStore<'a>,
+    localization: LocalizationStore<'a>,
+    bootloader: BootloaderStore<'a>,
+    iscsi: IscsiStore<'a>,
 }
 
 impl<'a> Store<'a> {
@@ -24,7 +73,10 @@ impl<'a> Store<'a> {
         Ok(Self {
             users: UsersStore::new(connection.clone()).await?,
             software: SoftwareStore::new(connection.clone()).await?,
-            storage: StorageStore::new(connection).await?,
+            storage: StorageStore::new(connection.clone()).await?,
+            localization: LocalizationStore::new(connection.clone()).await?,
+            bootloader: BootloaderStore::new(connection.clone()).await?,
+            iscsi: IscsiStore::new(connection).await?,
         })
     }
 
@@ -48,21 +100,173 @@ impl<'a> Store<'a> {
             settings.user = Some(self.users.load().await?);
         }
 
+        if scopes.contains(&Scope::Localization) {
+            settings.localization = Some(self.localization.load().await?);
+        }
+
+        if scopes.contains(&Scope::Bootloader) {
+            settings.bootloader = Some(self.bootloader.load().await?);
+        }
+
+        if scopes.contains(&Scope::Iscsi) {
+            settings.iscsi = Some(self.iscsi.load().await?);
+        }
+
         // TODO: use try_join here
         Ok(settings)
     }
 
+    /// Unsets the given settings in the D-Bus service
+    ///
+    /// Unsetting the user name removes the first user and unsetting the iSCSI targets logs them
+    /// out. Nothing is changed if any of the settings cannot be unset (e.g., the product).
+    ///
+    /// * `attrs`: settings to unset (e.g., `"storage.lvm"` or `"root.password"`).
+    pub async fn unset(&self, attrs: &[String]) -> Result<(), Box<dyn Error>> {
+        let actions = attrs
+            .iter()
+            .map(|a| UnsetAction::for_setting(a))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (attr, action) in attrs.iter().zip(actions) {
+            match action {
+                UnsetAction::Rewrite(scope) => {
+                    let mut settings = self.load(Some(vec![scope])).await?;
+                    settings.unset(attr)?;
+                    self.store(&settings).await?;
+                }
+                UnsetAction::RemoveFirstUser => self.users.remove_first_user().await?,
+                UnsetAction::RemoveRootPassword => self.users.remove_root_password().await?,
+                UnsetAction::RemoveRootSshKey => self.users.remove_root_ssh_key().await?,
+                UnsetAction::Unselect(kind) => self.software.clear(kind).await?,
+                UnsetAction::LogoutIscsiTargets => self.iscsi.reset().await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Resets the settings of the given scopes in the D-Bus service
+    ///
+    /// The selected product and the language are kept, as they cannot be unselected.
+    pub async fn reset(&self, scopes: &[Scope]) -> Result<(), Box<dyn Error>> {
+        if scopes.contains(&Scope::Software) {
+            let software = SoftwareSettings {
+                patterns: Some(vec![]),
+                packages: Some(vec![]),
+                ..Default::default()
+            };
+            self.software.store(&software).await?;
+        }
+        if scopes.contains(&Scope::Users) {
+            self.users.reset().await?;
+        }
+        if scopes.contains(&Scope::Iscsi) {
+            self.iscsi.reset().await?;
+        }
+        if scopes.contains(&Scope::Storage) {
+            self.storage.store(&Default::default()).await?;
+        }
+        if scopes.contains(&Scope::Bootloader) {
+            self.bootloader.store(&Default::default()).await?;
+        }
+        Ok(())
+    }
+
     /// Stores the given installation settings in the D-Bus service
+    ///
+    /// The iSCSI targets are logged in before calculating the storage proposal, so their devices
+    /// can be used.
     pub async fn store(&self, settings: &InstallSettings) -> Result<(), Box<dyn Error>> {
+        if let Some(localization) = &settings.localization {
+            self.localization.store(localization).await?;
+        }
         if let Some(software) = &settings.software {
             self.software.store(software).await?;
         }
         if let Some(user) = &settings.user {
             self.users.store(user).await?;
         }
+        if let Some(iscsi) = &settings.iscsi {
+            self.iscsi.store(iscsi).await?;
+        }
         if let Some(storage) = &settings.storage {
             self.storage.store(storage).await?;
         }
+        if let Some(bootloader) = &settings.bootloader {
+            self.bootloader.store(bootloader).await?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unset_users() {
+        let action = |attr| UnsetAction::for_setting(attr).unwrap();
+        assert_eq!(action("user.user_name"), UnsetAction::RemoveFirstUser);
+        assert_eq!(action("user.full_name"), UnsetAction::Rewrite(Scope::Users));
+        assert_eq!(action("root.password"), UnsetAction::RemoveRootPassword);
+        assert_eq!(action("root.ssh_public_key"), UnsetAction::RemoveRootSshKey);
+    }
+
+    #[test]
+    fn test_unset_software() {
+        assert_eq!(
+            UnsetAction::for_setting("software.patterns").unwrap(),
+            UnsetAction::Unselect(ResolvableType::Pattern)
+        );
+        assert_eq!(
+            UnsetAction::for_setting("software.packages").unwrap(),
+            UnsetAction::Unselect(ResolvableType::Package)
+        );
+        let error = UnsetAction::for_setting("software.product").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Setting 'software.product' cannot be unset"
+        );
+    }
+
+    #[test]
+    fn test_unset_storage() {
+        for attr in [
+            "storage.lvm",
+            "storage.encryption.method",
+            "storage.devices",
+        ] {
+            assert_eq!(
+                UnsetAction::for_setting(attr).unwrap(),
+                UnsetAction::Rewrite(Scope::Storage)
+            );
+        }
+    }
+
+    #[test]
+    fn test_unset_localization() {
+        for attr in ["localization.language", "localization.keyboard"] {
+            assert!(matches!(
+                UnsetAction::for_setting(attr),
+                Err(SettingsError::CannotUnset(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unset_bootloader() {
+        assert_eq!(
+            UnsetAction::for_setting("bootloader.timeout").unwrap(),
+            UnsetAction::Rewrite(Scope::Bootloader)
+        );
+    }
+
+    #[test]
+    fn test_unset_iscsi() {
+        assert_eq!(
+            UnsetAction::for_setting("iscsi.targets").unwrap(),
+            UnsetAction::LogoutIscsiTargets
+        );
+        assert!(UnsetAction::for_setting("iscsi.initiator").is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use crate::bootloader::{BootloaderClient, BootloaderConfig};
+use crate::error::ServiceError;
+use crate::install_settings::BootloaderSettings;
+use std::error::Error;
+use zbus::Connection;
+
+/// Loads and stores the bootloader settings from/to the D-Bus service.
+pub struct BootloaderStore<'a> {
+    bootloader_client: BootloaderClient<'a>,
+}
+
+impl<'a> BootloaderStore<'a> {
+    pub async fn new(connection: Connection) -> Result<BootloaderStore<'a>, ServiceError> {
+        Ok(Self {
+            bootloader_client: BootloaderClient::new(connection).await?,
+        })
+    }
+
+    pub async fn load(&self) -> Result<BootloaderSettings, Box<dyn Error>> {
+        let config = self.bootloader_client.config().await?;
+        Ok(BootloaderSettings {
+            bootloader_type: config.bootloader_type,
+            timeout: config.timeout,
+            extra_kernel_params: config.extra_kernel_params,
+            secure_boot: config.secure_boot,
+        })
+    }
+
+    pub async fn store(&self, settings: &BootloaderSettings) -> Result<(), Box<dyn Error>> {
+        let config = BootloaderConfig {
+            bootloader_type: settings.bootloader_type,
+            timeout: settings.timeout,
+            extra_kernel_params: settings.extra_kernel_params.clone(),
+            secure_boot: settings.secure_boot,
+        };
+        self.bootloader_client.set_config(config).await?;
+        Ok(())
+    }
+}
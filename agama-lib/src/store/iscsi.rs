@@ -0,0 +1,86 @@
+use crate::error::ServiceError;
+use crate::install_settings::{IscsiSettings, IscsiTarget};
+use crate::settings::SettingValue;
+use crate::storage::{IscsiClient, IscsiStartup, DEFAULT_ISCSI_PORT};
+use std::error::Error;
+use zbus::Connection;
+
+/// Loads and stores the iSCSI settings from/to the D-Bus service.
+pub struct IscsiStore<'a> {
+    iscsi_client: IscsiClient<'a>,
+}
+
+impl<'a> IscsiStore<'a> {
+    pub async fn new(connection: Connection) -> Result<IscsiStore<'a>, ServiceError> {
+        Ok(Self {
+            iscsi_client: IscsiClient::new(connection).await?,
+        })
+    }
+
+    /// Loads the initiator name and the connected targets
+    ///
+    /// The targets configured from the iSCSI Boot Firmware Table are left out, as they are not
+    /// handled by the installer. The credentials cannot be read back.
+    pub async fn load(&self) -> Result<IscsiSettings, Box<dyn Error>> {
+        let initiator = self.iscsi_client.initiator_name().await?;
+        let targets = self
+            .iscsi_client
+            .nodes()
+            .await?
+            .into_iter()
+            .filter(|n| n.connected && !n.ibft)
+            .map(|n| IscsiTarget {
+                startup: SettingValue(n.startup).try_into().ok(),
+                name: n.target,
+                address: n.address,
+                port: Some(n.port),
+                ..Default::default()
+            })
+            .collect();
+        Ok(IscsiSettings {
+            initiator: Some(initiator).filter(|i| !i.is_empty()),
+            targets,
+        })
+    }
+
+    /// Discovers and logs in the targets which are not connected yet
+    pub async fn store(&self, settings: &IscsiSettings) -> Result<(), Box<dyn Error>> {
+        if let Some(initiator) = &settings.initiator {
+            self.iscsi_client.set_initiator_name(initiator).await?;
+        }
+
+        for target in &settings.targets {
+            let port = target.port.unwrap_or(DEFAULT_ISCSI_PORT);
+            let auth = target.auth();
+            let known = self.iscsi_client.nodes().await?;
+            let node = known
+                .iter()
+                .find(|n| n.matches(&target.name, Some(&target.address), Some(port)));
+            if node.is_some_and(|n| n.connected) {
+                continue;
+            }
+            if node.is_none() {
+                self.iscsi_client
+                    .discover(&target.address, port, &auth)
+                    .await?;
+            }
+            let node = self
+                .iscsi_client
+                .find_node(&target.name, Some(&target.address), Some(port))
+                .await?;
+            let startup = target.startup.unwrap_or(IscsiStartup::Onboot);
+            self.iscsi_client.login(&node, &auth, startup).await?;
+        }
+        Ok(())
+    }
+
+    /// Logs out all the connected targets, except the ones from the iSCSI Boot Firmware Table
+    pub async fn reset(&self) -> Result<(), Box<dyn Error>> {
+        for node in self.iscsi_client.nodes().await? {
+            if node.connected && !node.ibft {
+                self.iscsi_client.logout(&node).await?;
+            }
+        }
+        Ok(())
+    }
+}
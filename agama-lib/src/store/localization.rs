@@ -0,0 +1,50 @@
+use crate::error::{ServiceError, WrongParameter};
+use crate::install_settings::LocalizationSettings;
+use crate::localization::LocalizationClient;
+use std::error::Error;
+use zbus::Connection;
+
+/// Loads and stores the localization settings from/to the D-Bus service.
+pub struct LocalizationStore<'a> {
+    localization_client: LocalizationClient<'a>,
+}
+
+impl<'a> LocalizationStore<'a> {
+    pub async fn new(connection: Connection) -> Result<LocalizationStore<'a>, ServiceError> {
+        Ok(Self {
+            localization_client: LocalizationClient::new(connection).await?,
+        })
+    }
+
+    pub async fn load(&self) -> Result<LocalizationSettings, Box<dyn Error>> {
+        let language = self.localization_client.language().await?;
+        let keyboard = self.localization_client.keymap().await?;
+        Ok(LocalizationSettings { language, keyboard })
+    }
+
+    pub async fn store(&self, settings: &LocalizationSettings) -> Result<(), Box<dyn Error>> {
+        if let Some(language) = &settings.language {
+            let languages = self.localization_client.languages().await?;
+            let ids: Vec<String> = languages.into_iter().map(|l| l.id).collect();
+            if !ids.contains(language) {
+                return Err(Box::new(WrongParameter::UnknownLanguage(
+                    language.clone(),
+                    ids,
+                )));
+            }
+            self.localization_client.select_language(language).await?;
+        }
+        if let Some(keyboard) = &settings.keyboard {
+            let keymaps = self.localization_client.keymaps().await?;
+            let ids: Vec<String> = keymaps.into_iter().map(|k| k.id).collect();
+            if !ids.contains(keyboard) {
+                return Err(Box::new(WrongParameter::UnknownKeymap(
+                    keyboard.clone(),
+                    ids,
+                )));
+            }
+            self.localization_client.set_keymap(keyboard).await?;
+        }
+        Ok(())
+    }
+}
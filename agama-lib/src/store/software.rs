@@ -1,6 +1,6 @@
 use crate::error::{ServiceError, WrongParameter};
 use crate::install_settings::SoftwareSettings;
-use crate::software::SoftwareClient;
+use crate::software::{ResolvableType, SoftwareClient};
 use std::error::Error;
 use zbus::Connection;
 
@@ -18,9 +18,19 @@ impl<'a> SoftwareStore<'a> {
 
     pub async fn load(&self) -> Result<SoftwareSettings, Box<dyn Error>> {
         let product = self.software_client.product().await?;
+        let patterns = self
+            .software_client
+            .selected_resolvables(ResolvableType::Pattern)
+            .await?;
+        let packages = self
+            .software_client
+            .selected_resolvables(ResolvableType::Package)
+            .await?;
 
         Ok(SoftwareSettings {
             product: Some(product),
+            patterns: Some(patterns),
+            packages: Some(packages),
         })
     }
 
@@ -28,13 +38,33 @@ impl<'a> SoftwareStore<'a> {
         if let Some(product) = &settings.product {
             let products = self.software_client.products().await?;
             let ids: Vec<String> = products.into_iter().map(|p| p.id).collect();
-            if ids.contains(&product) {
+            if ids.contains(product) {
                 self.software_client.select_product(product).await?;
             } else {
-                return Err(Box::new(WrongParameter::UnknownProduct(product.clone(), ids)));
+                return Err(Box::new(WrongParameter::UnknownProduct(
+                    product.clone(),
+                    ids,
+                )));
             }
-            
         }
+
+        if let Some(patterns) = &settings.patterns {
+            self.software_client
+                .select_resolvables(ResolvableType::Pattern, patterns)
+                .await?;
+        }
+
+        if let Some(packages) = &settings.packages {
+            self.software_client
+                .select_resolvables(ResolvableType::Package, packages)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Unselects all the resolvables of the given type
+    pub async fn clear(&self, kind: ResolvableType) -> Result<(), Box<dyn Error>> {
+        self.software_client.select_resolvables(kind, &[]).await?;
         Ok(())
     }
 }
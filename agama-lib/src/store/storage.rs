@@ -1,6 +1,8 @@
 use crate::error::ServiceError;
-use crate::install_settings::StorageSettings;
-use crate::storage::StorageClient;
+use crate::install_settings::{
+    BtrfsSettings, Device, EncryptionSettings, SpaceAction, StorageSettings, Subvolume,
+};
+use crate::storage::{validate_layout, BtrfsVolume, ProposalSettings, StorageClient};
 use std::default::Default;
 use std::error::Error;
 use zbus::Connection;
@@ -17,19 +19,94 @@ impl<'a> StorageStore<'a> {
         })
     }
 
-    // TODO: read the settings from the service
     pub async fn load(&self) -> Result<StorageSettings, Box<dyn Error>> {
-        Ok(Default::default())
+        // the proposal does not exist until it is calculated
+        let Ok(candidate_devices) = self.storage_client.candidate_devices().await else {
+            return Ok(Default::default());
+        };
+        let devices = candidate_devices
+            .into_iter()
+            .map(|name| Device::with_name(&name))
+            .collect();
+        let encryption_password = self.storage_client.encryption_password().await?;
+        let encryption = EncryptionSettings {
+            method: self.storage_client.encryption_method().await?,
+            pbkdf: self.storage_client.encryption_pbkdf().await?,
+        };
+        let btrfs = self
+            .storage_client
+            .btrfs()
+            .await?
+            .filter(|b| !b.is_empty())
+            .map(|b| BtrfsSettings {
+                snapshots: b.snapshots,
+                subvolumes: b
+                    .subvolumes
+                    .into_iter()
+                    .map(|(path, cow)| Subvolume {
+                        path,
+                        copy_on_write: Some(cow),
+                    })
+                    .collect(),
+            });
+        Ok(StorageSettings {
+            lvm: Some(self.storage_client.lvm().await?),
+            boot_device: self.storage_client.boot_device().await?,
+            encryption_password: Some(encryption_password).filter(|p| !p.is_empty()),
+            encryption: Some(encryption).filter(|e| e.method.is_some() || e.pbkdf.is_some()),
+            btrfs,
+            expert: Some(self.storage_client.layout().await?).filter(|l| !l.is_empty()),
+            devices,
+            space_policy: self.storage_client.space_policy().await?,
+            space_actions: self
+                .storage_client
+                .space_actions()
+                .await?
+                .into_iter()
+                .map(|(device, action)| SpaceAction { device, action })
+                .collect(),
+        })
     }
 
     pub async fn store(&self, settings: &StorageSettings) -> Result<(), Box<dyn Error>> {
-        self.storage_client
-            .calculate(
-                settings.devices.iter().map(|d| d.name.clone()).collect(),
-                settings.encryption_password.clone().unwrap_or_default(),
-                settings.lvm.unwrap_or_default(),
-            )
-            .await?;
+        let encryption = settings.encryption.clone().unwrap_or_default();
+        let btrfs = settings.btrfs.clone().unwrap_or_default();
+        let layout = settings.expert.clone().unwrap_or_default();
+        let available = if settings.devices.iter().any(|d| d.name.is_none()) || !layout.is_empty() {
+            self.storage_client.available_devices().await?
+        } else {
+            vec![]
+        };
+        validate_layout(&layout, &available)?;
+
+        let proposal = ProposalSettings {
+            candidate_devices: settings
+                .devices
+                .iter()
+                .map(|d| d.resolve(&available))
+                .collect::<Result<_, _>>()?,
+            boot_device: settings.boot_device.clone(),
+            lvm: settings.lvm.unwrap_or_default(),
+            encryption_password: settings.encryption_password.clone().unwrap_or_default(),
+            encryption_method: encryption.method,
+            encryption_pbkdf: encryption.pbkdf,
+            space_policy: settings.space_policy,
+            space_actions: settings
+                .space_actions
+                .iter()
+                .map(|a| (a.device.clone(), a.action))
+                .collect(),
+            btrfs: BtrfsVolume {
+                snapshots: btrfs.snapshots,
+                subvolumes: btrfs
+                    .subvolumes
+                    .into_iter()
+                    .map(|s| (s.path, s.copy_on_write.unwrap_or(true)))
+                    .collect(),
+            },
+            layout,
+        };
+        self.storage_client.calculate(proposal).await?;
         Ok(())
     }
 }
@@ -1,6 +1,6 @@
+use crate::error::WrongParameter;
 use crate::install_settings::{FirstUserSettings, RootUserSettings, UserSettings};
 use crate::users::{FirstUser, UsersClient};
-use crate::error::WrongParameter;
 use std::error::Error;
 use zbus::Connection;
 
@@ -48,7 +48,39 @@ impl<'a> UsersStore<'a> {
         Ok(())
     }
 
+    /// Removes the first user, the root password and the root SSH key
+    pub async fn reset(&self) -> Result<(), Box<dyn Error>> {
+        self.users_client.remove_first_user().await?;
+        self.users_client.remove_root_password().await?;
+        self.users_client.set_root_sshkey("").await?;
+        Ok(())
+    }
+
+    /// Removes the first user
+    pub async fn remove_first_user(&self) -> Result<(), Box<dyn Error>> {
+        self.users_client.remove_first_user().await?;
+        Ok(())
+    }
+
+    /// Removes the root password
+    pub async fn remove_root_password(&self) -> Result<(), Box<dyn Error>> {
+        self.users_client.remove_root_password().await?;
+        Ok(())
+    }
+
+    /// Removes the root SSH public key
+    pub async fn remove_root_ssh_key(&self) -> Result<(), Box<dyn Error>> {
+        self.users_client.set_root_sshkey("").await?;
+        Ok(())
+    }
+
+    /// Stores the first user, removing it if it has no user name
     async fn store_first_user(&self, settings: &FirstUserSettings) -> Result<(), Box<dyn Error>> {
+        if settings.user_name.as_deref().unwrap_or_default().is_empty() {
+            self.users_client.remove_first_user().await?;
+            return Ok(());
+        }
+
         let first_user = FirstUser {
             user_name: settings.user_name.clone().unwrap_or_default(),
             full_name: settings.full_name.clone().unwrap_or_default(),
@@ -58,7 +90,7 @@ impl<'a> UsersStore<'a> {
         };
         let (success, issues) = self.users_client.set_first_user(&first_user).await?;
         if !success {
-            return Err(Box::new(WrongParameter::WrongUser(issues)));  
+            return Err(Box::new(WrongParameter::WrongUser(issues)));
         }
         Ok(())
     }
@@ -2,7 +2,7 @@
 
 use super::proxies::Users1Proxy;
 use crate::error::ServiceError;
-use crate::settings::{SettingValue, Settings};
+use crate::settings::{SettingValue, Settings, SettingsError};
 use serde::Serialize;
 use zbus::Connection;
 
@@ -21,16 +21,17 @@ pub struct FirstUser {
     pub data: std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
 }
 
+/// First user data as returned by the D-Bus service
+type FirstUserData = (
+    String,
+    String,
+    String,
+    bool,
+    std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
+);
+
 impl FirstUser {
-    pub fn from_dbus(
-        dbus_data: zbus::Result<(
-            String,
-            String,
-            String,
-            bool,
-            std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
-        )>,
-    ) -> zbus::Result<Self> {
+    pub fn from_dbus(dbus_data: zbus::Result<FirstUserData>) -> zbus::Result<Self> {
         let data = dbus_data?;
         Ok(Self {
             full_name: data.0,
@@ -43,13 +44,18 @@ impl FirstUser {
 }
 
 impl Settings for FirstUser {
-    fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), &'static str> {
+    fn set(&mut self, attr: &str, value: SettingValue) -> Result<(), SettingsError> {
+        let key = attr.to_string();
         match attr {
             "full_name" => self.full_name = value.try_into()?,
             "user_name" => self.user_name = value.try_into()?,
             "password" => self.password = value.try_into()?,
-            "autologin" => self.autologin = value.try_into()?,
-            _ => return Err("unknown attribute"),
+            "autologin" => {
+                self.autologin = value
+                    .try_into()
+                    .map_err(|source| SettingsError::WrongValue { key, source })?
+            }
+            _ => return Err(SettingsError::UnknownKey(key)),
         }
         Ok(())
     }
@@ -78,10 +84,7 @@ impl<'a> UsersClient<'a> {
         value: &str,
         encrypted: bool,
     ) -> Result<u32, ServiceError> {
-        Ok(self
-            .users_proxy
-            .set_root_password(value, encrypted)
-            .await?)
+        Ok(self.users_proxy.set_root_password(value, encrypted).await?)
     }
 
     /// Whether the root password is set or not
@@ -99,6 +102,16 @@ impl<'a> UsersClient<'a> {
         Ok(self.users_proxy.set_root_sshkey(value).await?)
     }
 
+    /// Removes the first user
+    pub async fn remove_first_user(&self) -> Result<u32, ServiceError> {
+        Ok(self.users_proxy.remove_first_user().await?)
+    }
+
+    /// Removes the root password
+    pub async fn remove_root_password(&self) -> Result<u32, ServiceError> {
+        Ok(self.users_proxy.remove_root_password().await?)
+    }
+
     /// Set the configuration for the first user
     pub async fn set_first_user(
         &self,